@@ -0,0 +1,84 @@
+// Compute shader
+
+struct Frustum {
+    planes: array<vec4<f32>, 6>;
+    instance_count: u32;
+};
+[[group(0), binding(0)]]
+var<uniform> frustum: Frustum;
+
+struct CullInstance {
+    transform_matrix: mat4x4<f32>;
+    slot: u32;
+};
+
+struct CullInstances {
+    data: array<CullInstance>;
+};
+[[group(0), binding(1)]]
+var<storage, read> instances: CullInstances;
+
+struct MeshSlot {
+    bounds: vec4<f32>;
+    base: u32;
+};
+
+struct MeshSlots {
+    data: array<MeshSlot>;
+};
+[[group(0), binding(2)]]
+var<storage, read> slots: MeshSlots;
+
+struct DrawIndexedIndirect {
+    index_count: u32;
+    instance_count: atomic<u32>;
+    first_index: u32;
+    base_vertex: i32;
+    first_instance: u32;
+};
+
+struct DrawIndexedIndirects {
+    data: array<DrawIndexedIndirect>;
+};
+[[group(0), binding(3)]]
+var<storage, read_write> draws: DrawIndexedIndirects;
+
+struct VisibleInstances {
+    data: array<mat4x4<f32>>;
+};
+[[group(0), binding(4)]]
+var<storage, read_write> visible: VisibleInstances;
+
+fn is_visible(transform_matrix: mat4x4<f32>, bounds: vec4<f32>) -> bool {
+    let center = transform_matrix * vec4<f32>(bounds.xyz, 1.0);
+    let scale = max(
+        length(transform_matrix[0].xyz),
+        max(length(transform_matrix[1].xyz), length(transform_matrix[2].xyz)),
+    );
+    let radius = bounds.w * scale;
+
+    for (var i = 0; i < 6; i = i + 1) {
+        let plane = frustum.planes[i];
+        if (dot(plane.xyz, center.xyz / center.w) + plane.w < -radius) {
+            return false;
+        }
+    }
+    return true;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let index = id.x;
+    if (index >= frustum.instance_count) {
+        return;
+    }
+
+    let instance = instances.data[index];
+    let slot = slots.data[instance.slot];
+    if (!is_visible(instance.transform_matrix, slot.bounds)) {
+        return;
+    }
+
+    let visible_index = atomicAdd(&draws.data[instance.slot].instance_count, 1u);
+    visible.data[slot.base + visible_index] = instance.transform_matrix;
+}
@@ -0,0 +1,33 @@
+// Vertex shader
+
+struct ViewProjection {
+    matrix: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> view_proj: ViewProjection;
+
+struct VertexInput {
+    [[location(0)]] position: vec4<f32>;
+    [[location(1)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = model.color;
+    out.clip_position = view_proj.matrix * model.position;
+    return out;
+}
+
+
+// Fragment shader
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,80 @@
+// Vertex shader
+
+let CURVE_KEYS: u32 = 8u;
+
+struct ParticleRender {
+    view_proj: mat4x4<f32>;
+    camera_right: vec4<f32>;
+    camera_up: vec4<f32>;
+    colors: array<vec4<f32>, 8>;
+    // size at x
+    sizes: array<vec4<f32>, 8>;
+};
+[[group(0), binding(0)]]
+var<uniform> render: ParticleRender;
+
+struct Particle {
+    position: vec4<f32>;
+    velocity: vec4<f32>;
+};
+
+struct Particles {
+    data: array<Particle>;
+};
+[[group(0), binding(1)]]
+var<storage, read> particles: Particles;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+    [[location(1)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[builtin(instance_index)]] instance_index: u32,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let particle = particles.data[instance_index];
+
+    var out: VertexOutput;
+    out.uv = corner;
+
+    if (particle.position.w >= particle.velocity.w) {
+        // Dead particle is culled outside of clip space.
+        out.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        out.color = vec4<f32>(0.0);
+        return out;
+    }
+
+    let life = clamp(particle.position.w / particle.velocity.w, 0.0, 1.0) * f32(CURVE_KEYS - 1u);
+    let key = u32(floor(life));
+    let next = min(key + 1u, CURVE_KEYS - 1u);
+    let t = fract(life);
+
+    let size = mix(render.sizes[key].x, render.sizes[next].x, t);
+    out.color = mix(render.colors[key], render.colors[next], t);
+
+    let position = particle.position.xyz
+        + (render.camera_right.xyz * corner.x + render.camera_up.xyz * corner.y) * size * 0.5;
+    out.clip_position = render.view_proj * vec4<f32>(position, 1.0);
+    return out;
+}
+
+
+// Fragment shader
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let falloff = 1.0 - clamp(length(in.uv), 0.0, 1.0);
+    return vec4<f32>(in.color.rgb, in.color.a * falloff);
+}
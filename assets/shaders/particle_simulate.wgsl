@@ -0,0 +1,77 @@
+// Compute shader
+
+struct Simulation {
+    // xyz for position, w for velocity spread
+    emitter: vec4<f32>;
+    // xyz for velocity, w for lifetime
+    velocity: vec4<f32>;
+    // xyz for gravity, w for delta time
+    gravity: vec4<f32>;
+    // spawn start, spawn count, max particles, serial of first spawning particle
+    spawn: vec4<u32>;
+    // seed, lifetime variance as bits
+    seed: vec4<u32>;
+};
+[[group(0), binding(0)]]
+var<uniform> simulation: Simulation;
+
+struct Particle {
+    // xyz for position, w for age
+    position: vec4<f32>;
+    // xyz for velocity, w for lifetime. Dead when age >= lifetime
+    velocity: vec4<f32>;
+};
+
+struct Particles {
+    data: array<Particle>;
+};
+[[group(0), binding(1)]]
+var<storage, read_write> particles: Particles;
+
+fn hash(value: u32) -> u32 {
+    var state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// 0..1
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = hash(*state);
+    return f32(*state) / 4294967295.0;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let index = id.x;
+    let max_particles = simulation.spawn.z;
+    if (index >= max_particles) {
+        return;
+    }
+
+    let relative = (index + max_particles - simulation.spawn.x) % max_particles;
+    if (relative < simulation.spawn.y) {
+        var state = hash(simulation.seed.x ^ hash(simulation.spawn.w + relative));
+        let spread = simulation.emitter.w * (vec3<f32>(
+            random(&state),
+            random(&state),
+            random(&state),
+        ) * 2.0 - 1.0);
+        let variance = bitcast<f32>(simulation.seed.y);
+        let lifetime = simulation.velocity.w * (1.0 - variance * random(&state));
+
+        particles.data[index].position = vec4<f32>(simulation.emitter.xyz, 0.0);
+        particles.data[index].velocity = vec4<f32>(simulation.velocity.xyz + spread, lifetime);
+        return;
+    }
+
+    var particle = particles.data[index];
+    if (particle.position.w >= particle.velocity.w) {
+        return;
+    }
+
+    let delta = simulation.gravity.w;
+    let velocity = particle.velocity.xyz + simulation.gravity.xyz * delta;
+    particle.velocity = vec4<f32>(velocity, particle.velocity.w);
+    particle.position = vec4<f32>(particle.position.xyz + velocity * delta, particle.position.w + delta);
+    particles.data[index] = particle;
+}
@@ -0,0 +1,42 @@
+// Vertex shader
+
+struct Sky {
+    // inverse of projection * rotation only view
+    inv_view_proj: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> sky: Sky;
+
+[[group(0), binding(1)]]
+var sky_texture: texture_cube<f32>;
+[[group(0), binding(2)]]
+var sky_sampler: sampler;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] ndc: vec2<f32>;
+};
+
+// Single triangle covers whole screen.
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    let ndc = vec2<f32>(
+        f32(vertex_index == 1u) * 4.0 - 1.0,
+        f32(vertex_index == 2u) * 4.0 - 1.0,
+    );
+
+    var out: VertexOutput;
+    out.ndc = ndc;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    return out;
+}
+
+
+// Fragment shader
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let world = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = normalize(world.xyz / world.w);
+    return textureSample(sky_texture, sky_sampler, direction);
+}
@@ -21,6 +21,7 @@ struct InstanceInput {
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    [[location(1)]] world_position: vec3<f32>;
 };
 
 [[stage(vertex)]]
@@ -35,9 +36,12 @@ fn vs_main(
         instance.transform_matrix_3,
     );
 
+    let world_position = transform_matrix * model.position;
+
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = view_proj.matrix * transform_matrix * model.position;
+    out.world_position = world_position.xyz / world_position.w;
+    out.clip_position = view_proj.matrix * world_position;
     return out;
 }
 
@@ -48,3 +52,31 @@ fn vs_main(
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
     return in.color;
 }
+
+// Only bound to the pipeline of fs_reflect.
+
+struct Environment {
+    // xyz is the eye position, w is reflectivity
+    eye_reflectivity: vec4<f32>;
+};
+[[group(1), binding(0)]]
+var<uniform> environment: Environment;
+
+[[group(1), binding(1)]]
+var environment_texture: texture_cube<f32>;
+[[group(1), binding(2)]]
+var environment_sampler: sampler;
+
+[[stage(fragment)]]
+fn fs_reflect(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let to_eye = normalize(environment.eye_reflectivity.xyz - in.world_position);
+    // Flat normal of the triangle, turned to the eye.
+    var normal = normalize(cross(dpdx(in.world_position), dpdy(in.world_position)));
+    normal = select(normal, -normal, dot(normal, to_eye) < 0.0);
+
+    let reflected = textureSample(environment_texture, environment_sampler, reflect(-to_eye, normal));
+    return vec4<f32>(
+        mix(in.color.rgb, reflected.rgb, environment.eye_reflectivity.w),
+        in.color.a,
+    );
+}
@@ -0,0 +1,193 @@
+/*!
+Derive macros of rust_try_lib. Generated code refers to the items through `::rust_try_lib`.
+*/
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Ident, LitInt, Type};
+
+/**Implements `Vertex` for a `#[repr(C)]` struct with named fields.
+
+Each field becomes vertex attributes. Format comes from the type:
+`f32`, `u32`, `i32` and arrays of them up to 4, or arrays of those arrays which take a location per inner array, like matrices.
+`#[format(Float32x4)]` overrides it for other types.
+`#[location(n)]` sets the shader location of a field. Fields without it take the next one.
+`#[step_mode(Instance)]` on the struct makes it per instance.
+
+```ignore
+#[derive(Clone, Copy, Vertex)]
+#[repr(C)]
+#[step_mode(Instance)]
+pub struct Instance {
+    #[location(2)]
+    transform_matrix: [[f32; 4]; 4],
+}
+```
+*/
+#[proc_macro_derive(Vertex, attributes(location, format, step_mode))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn vertex(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Vertex can't be derived for generic structs",
+        ));
+    }
+    if !is_repr_c(input)? {
+        return Err(Error::new_spanned(
+            name,
+            "Vertex needs #[repr(C)] for a stable field layout",
+        ));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    name,
+                    "Vertex needs a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new_spanned(name, "Vertex needs a struct")),
+    };
+
+    let mut step_mode = Ident::new("Vertex", Span::call_site());
+    for attr in &input.attrs {
+        if attr.path().is_ident("step_mode") {
+            step_mode = attr.parse_args()?;
+            if step_mode != "Vertex" && step_mode != "Instance" {
+                return Err(Error::new_spanned(
+                    step_mode,
+                    "step_mode should be Vertex or Instance",
+                ));
+            }
+        }
+    }
+
+    let mut attributes = Vec::new();
+    let mut next_location = 0u32;
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let mut location = next_location;
+        let mut format = None;
+        for attr in &field.attrs {
+            if attr.path().is_ident("location") {
+                location = attr.parse_args::<LitInt>()?.base10_parse()?;
+            } else if attr.path().is_ident("format") {
+                format = Some(attr.parse_args::<Ident>()?);
+            }
+        }
+
+        //Array of arrays takes a location per inner array.
+        let (format, element, count) = match format {
+            Some(format) => (format, None, 1),
+            None => infer_format(&field.ty)?,
+        };
+        for index in 0..count {
+            let offset = match element {
+                Some(ref element) => quote! {
+                    ::core::mem::offset_of!(#name, #field_name)
+                        + #index * ::core::mem::size_of::<#element>()
+                },
+                None => quote! { ::core::mem::offset_of!(#name, #field_name) },
+            };
+            let shader_location = location + index as u32;
+            attributes.push(quote! {
+                ::rust_try_lib::wgpu::VertexAttribute {
+                    format: ::rust_try_lib::wgpu::VertexFormat::#format,
+                    offset: (#offset) as ::rust_try_lib::wgpu::BufferAddress,
+                    shader_location: #shader_location,
+                }
+            });
+        }
+        next_location = location + count as u32;
+    }
+
+    Ok(quote! {
+        impl ::rust_try_lib::graphics::elements::Vertex for #name {
+            fn buffer_layout<'a>() -> ::rust_try_lib::wgpu::VertexBufferLayout<'a> {
+                const ATTRIBUTES: &[::rust_try_lib::wgpu::VertexAttribute] = &[#(#attributes),*];
+                ::rust_try_lib::wgpu::VertexBufferLayout {
+                    array_stride: ::core::mem::size_of::<#name>() as ::rust_try_lib::wgpu::BufferAddress,
+                    step_mode: ::rust_try_lib::wgpu::VertexStepMode::#step_mode,
+                    attributes: ATTRIBUTES,
+                }
+            }
+        }
+    })
+}
+
+fn is_repr_c(input: &DeriveInput) -> Result<bool, Error> {
+    let mut repr_c = false;
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("C") {
+                    repr_c = true;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(repr_c)
+}
+
+///Format, inner array type and count of locations.
+fn infer_format(ty: &Type) -> Result<(Ident, Option<Type>, usize), Error> {
+    let unknown = || {
+        Error::new_spanned(
+            ty,
+            "can't infer the vertex format of this type, use #[format(..)]",
+        )
+    };
+
+    match ty {
+        Type::Array(array) => {
+            let len = array_len(&array.len).ok_or_else(unknown)?;
+            match *array.elem {
+                Type::Array(ref inner) => {
+                    let inner_len = array_len(&inner.len).ok_or_else(unknown)?;
+                    let format = scalar_format(&inner.elem, inner_len).ok_or_else(unknown)?;
+                    Ok((format, Some((*array.elem).clone()), len))
+                }
+                ref elem => Ok((scalar_format(elem, len).ok_or_else(unknown)?, None, 1)),
+            }
+        }
+        ty => Ok((scalar_format(ty, 1).ok_or_else(unknown)?, None, 1)),
+    }
+}
+
+fn array_len(len: &Expr) -> Option<usize> {
+    match len {
+        Expr::Lit(lit) => match lit.lit {
+            syn::Lit::Int(ref int) => int.base10_parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn scalar_format(ty: &Type, len: usize) -> Option<Ident> {
+    let scalar = match ty.to_token_stream().to_string().as_str() {
+        "f32" => "Float32",
+        "u32" => "Uint32",
+        "i32" => "Sint32",
+        _ => return None,
+    };
+    let format = match len {
+        1 => scalar.to_string(),
+        2..=4 => format!("{}x{}", scalar, len),
+        _ => return None,
+    };
+    Some(Ident::new(&format, Span::call_site()))
+}
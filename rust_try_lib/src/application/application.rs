@@ -1,13 +1,19 @@
 use crate::{
-    application::Scene,
-    graphics::{Graphics, GraphicsConfig},
-    inputs::Inputs,
-    utils::Utils,
+    application::{EventBus, EventSender, Scene, UserEvent, WindowCommand, WindowCommands},
+    graphics::{Graphics, GraphicsConfig, GraphicsError},
+    inputs::{CursorRequest, InputRecording, InputReplay, Inputs},
+    utils::{Clock, Utils},
 };
 
+#[cfg(feature = "gamepad")]
+use crate::inputs::GamepadBackend;
+
 use std::{
     cell::Cell,
+    collections::HashSet,
+    path::PathBuf,
     sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
 };
 
 //kinda.. side-effect of my modular practice
@@ -21,38 +27,124 @@ pub struct Application {
 
     utils: Utils,
     inputs: Inputs,
+    ///None when the platform has no gamepad support.
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<GamepadBackend>,
     graphics: Graphics,
+    events: EventBus,
+    window_commands: WindowCommands,
+
+    replay: Option<InputReplay>,
+    recording_path: Option<PathBuf>,
 
-    event_loop: Cell<Option<EventLoop<()>>>,
+    event_loop: Cell<Option<EventLoop<UserEvent>>>,
+    event_sender: EventSender,
 
     frame_per_sec: f64,
+    ///Wall clock, so frames are limited whatever clock the time goes by.
+    last_frame_instant: Instant,
+
+    focused_window_ids: HashSet<WindowId>,
+    pause_when_unfocused: bool,
 
     _title: &'static str,
 }
 
 impl Application {
-    pub fn new(title: &'static str) -> Self {
-        let event_loop = EventLoop::new();
-        let graphics = pollster::block_on(Graphics::new(GraphicsConfig { title }, &event_loop));
+    ///Err when the window or the device can't be made.
+    pub fn new(title: &'static str) -> Result<Self, GraphicsError> {
+        let event_loop = EventLoop::with_user_event();
+        let event_sender = EventSender::new(event_loop.create_proxy());
+        let graphics = pollster::block_on(Graphics::new(GraphicsConfig { title }, &event_loop))?;
 
-        Self {
+        let mut inputs = Inputs::new();
+        for window_id in graphics.window_ids() {
+            if let Some(size) = graphics.window_size(*window_id) {
+                inputs.resize(*window_id, size);
+            }
+        }
+
+        Ok(Self {
             _title: title,
 
             event_loop: Cell::new(Some(event_loop)),
+            event_sender,
 
             frame_per_sec: 60.0,
+            last_frame_instant: Instant::now(),
+
+            //Not every platform tells focus of new windows.
+            focused_window_ids: graphics.primary_window_id().into_iter().collect(),
+            pause_when_unfocused: false,
 
             graphics,
             utils: Utils::new(),
-            inputs: Inputs::new(),
+            inputs,
+            #[cfg(feature = "gamepad")]
+            gamepads: GamepadBackend::new(),
+            events: EventBus::new(),
+            window_commands: WindowCommands::new(),
+
+            replay: None,
+            recording_path: None,
 
             scene: None,
-        }
+        })
     }
 
     pub fn graphics(&self) -> &Graphics {
         &self.graphics
     }
+
+    ///To set up input map before running.
+    pub fn inputs_mut(&mut self) -> &mut Inputs {
+        &mut self.inputs
+    }
+
+    ///To set up timers or the time scale before running.
+    pub fn utils_mut(&mut self) -> &mut Utils {
+        &mut self.utils
+    }
+
+    ///Time from the clock instead of the system, for deterministic runs.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.utils.set_clock(clock);
+    }
+
+    ///To subscribe listeners before running.
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    ///For other threads to send events to the scene and the event bus.
+    pub fn event_sender(&self) -> EventSender {
+        self.event_sender.clone()
+    }
+
+    ///Inputs are saved to the path when the application ends.
+    pub fn record_inputs(&mut self, path: impl Into<PathBuf>) {
+        self.inputs.start_recording();
+        self.recording_path = Some(path.into());
+    }
+
+    ///Live inputs are ignored until the recording ends. Frames get recorded time deltas.
+    pub fn replay_inputs(&mut self, recording: InputRecording) {
+        self.replay = Some(InputReplay::new(recording));
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    ///Whether any window of the application has focus.
+    pub fn is_focused(&self) -> bool {
+        !self.focused_window_ids.is_empty()
+    }
+
+    ///Scene doesn't get update while no window has focus. Rendering goes on.
+    pub fn set_pause_when_unfocused(&mut self, pause_when_unfocused: bool) {
+        self.pause_when_unfocused = pause_when_unfocused;
+    }
 }
 
 impl Application {
@@ -64,22 +156,180 @@ impl Application {
 
     fn resize(&mut self, window_id: WindowId, new_size: winit::dpi::PhysicalSize<u32>) {
         self.graphics.resize(window_id, new_size);
+        self.inputs.resize(window_id, new_size);
         if let Some(ref mut scene) = self.scene {
             scene.resize(window_id, new_size);
         }
     }
 
+    fn focus(&mut self, window_id: WindowId, focused: bool) {
+        if focused {
+            self.focused_window_ids.insert(window_id);
+        } else {
+            self.focused_window_ids.remove(&window_id);
+        }
+        if let Some(ref mut scene) = self.scene {
+            scene.focus(window_id, focused);
+        }
+    }
+
+    fn suspend(&mut self) {
+        self.graphics.suspend();
+        if let Some(ref mut scene) = self.scene {
+            scene.suspend();
+        }
+    }
+
+    fn resume(&mut self) {
+        //Some platforms send Resumed at start, without Suspended before.
+        if !self.graphics.is_suspended() {
+            return;
+        }
+        self.graphics.resume();
+        if let Some(ref mut scene) = self.scene {
+            scene.resume();
+        }
+    }
+
+    fn close_requested(&mut self, window_id: WindowId) {
+        if let Some(ref mut scene) = self.scene {
+            if !scene.close_requested(window_id) {
+                return;
+            }
+        }
+        self.remove_window(window_id);
+    }
+
+    fn remove_window(&mut self, window_id: WindowId) {
+        if self.graphics.window_size(window_id).is_none() {
+            return;
+        }
+        self.graphics.remove_window(window_id);
+        self.inputs.remove_window(window_id);
+        self.focused_window_ids.remove(&window_id);
+        if let Some(ref mut scene) = self.scene {
+            scene.window_closed(window_id);
+        }
+        if self.graphics.window_ids().len() == 0 {
+            Self::exit();
+        }
+    }
+
     fn pre_update(&mut self) {
+        if let Some(ref mut replay) = self.replay {
+            //Recorded window indexes start from the primary window.
+            let primary_window_id = self.graphics.primary_window_id();
+            let mut window_ids: Vec<WindowId> =
+                self.graphics.window_ids().into_iter().copied().collect();
+            window_ids.sort_by_key(|window_id| Some(*window_id) != primary_window_id);
+
+            match replay.advance(&mut self.inputs, &window_ids) {
+                Some(delta) => {
+                    self.utils.advance(delta);
+                    self.poll_gamepads();
+                    return;
+                }
+                None => self.replay = None,
+            }
+        }
+
         self.utils.pre_update();
+        //Inputs go by real time, so double clicks and chords work while paused.
+        self.inputs.set_time(self.utils.real_time());
         self.inputs.pre_update();
+        self.inputs.record_frame(self.utils.unscaled_time_delta());
+        self.poll_gamepads();
     }
 
+    ///Drained while replaying too, so they don't pile up till the end.
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepads(&mut self) {
+        if let Some(ref mut gamepads) = self.gamepads {
+            while let Some((gamepad_id, input)) = gamepads.next_input() {
+                if self.replay.is_none() {
+                    self.inputs.handle_gamepad_input(gamepad_id, input);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn poll_gamepads(&mut self) {}
+
     fn update(&mut self) {
-        self.graphics.update();
+        if let Err(error) = self.graphics.update() {
+            let exit = match self.scene {
+                Some(ref mut scene) => scene.graphics_failed(&error),
+                None => true,
+            };
+            if exit {
+                Self::exit();
+                return;
+            }
+        }
+
+        if self.pause_when_unfocused && !self.is_focused() {
+            return;
+        }
+        if let Some(ref mut scene) = self.scene {
+            scene.update(
+                &mut self.utils,
+                &self.inputs,
+                &mut self.events,
+                &mut self.window_commands,
+            );
+        }
+        self.events.dispatch_queued();
+        self.apply_cursor_requests();
+    }
 
+    fn apply_window_commands(&mut self, event_loop: &EventLoopWindowTarget<UserEvent>) {
+        for command in self.window_commands.take() {
+            match command {
+                WindowCommand::Create(config) => {
+                    match self.graphics.add_window(event_loop, &config) {
+                        Ok(window_id) => {
+                            if let Some(size) = self.graphics.window_size(window_id) {
+                                self.inputs.resize(window_id, size);
+                            }
+                            if let Some(ref mut scene) = self.scene {
+                                scene.window_opened(window_id, &self.graphics);
+                            }
+                        }
+                        Err(error) => {
+                            if let Some(ref mut scene) = self.scene {
+                                scene.window_failed(&config, &error);
+                            }
+                        }
+                    }
+                }
+                WindowCommand::Close(window_id) => self.remove_window(window_id),
+            }
+        }
+    }
+
+    fn apply_cursor_requests(&mut self) {
+        for (window_id, request) in self.inputs.take_cursor_requests() {
+            let result = match request {
+                CursorRequest::Mode(mode) => self.graphics.set_cursor_mode(window_id, mode),
+                CursorRequest::Icon(icon) => self.graphics.set_cursor_icon(window_id, icon),
+            };
+            match result {
+                Ok(()) => self.inputs.handle_cursor_request(window_id, request),
+                Err(error) => {
+                    if let Some(ref mut scene) = self.scene {
+                        scene.cursor_failed(window_id, &error);
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_user_event(&mut self, mut event: UserEvent) {
         if let Some(ref mut scene) = self.scene {
-            scene.update(&self.utils, &self.inputs);
+            scene.user_event(&mut event);
         }
+        event.fire(&mut self.events);
     }
 
     fn draw(&mut self) {
@@ -93,25 +343,27 @@ impl Application {
         self.event_loop
             .take()
             .unwrap()
-            .run(move |event, _event_loop, control_flow| {
+            .run(move |event, event_loop, control_flow| {
                 match event {
                     Event::NewEvents(start_cause) => match start_cause {
                         StartCause::Init => self.init(),
                         _ => {
                             // println!("------");
                             while 1.0 / self.frame_per_sec
-                                > self.utils.time_elapsed() - self.utils.time()
+                                > self.last_frame_instant.elapsed().as_secs_f64()
                             {
                                 std::hint::spin_loop();
                             }
+                            self.last_frame_instant = Instant::now();
                             self.pre_update();
                         }
                     },
                     Event::WindowEvent { window_id, event } => match event {
-                        WindowEvent::CloseRequested => {
-                            self.graphics.remove_window(window_id);
-                            if self.graphics.window_ids().len() == 0 {
-                                Self::exit();
+                        WindowEvent::CloseRequested => self.close_requested(window_id),
+                        WindowEvent::Focused(focused) => {
+                            self.focus(window_id, focused);
+                            if !self.is_replaying() {
+                                self.inputs.handle_window_input(window_id, event)
                             }
                         }
                         WindowEvent::Resized(new_inner_size) => {
@@ -120,17 +372,26 @@ impl Application {
                         WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                             self.resize(window_id, *new_inner_size)
                         }
-                        _ => self.inputs.handle_window_input(window_id, event),
+                        _ => {
+                            if !self.is_replaying() {
+                                self.inputs.handle_window_input(window_id, event)
+                            }
+                        }
                     },
                     Event::DeviceEvent { device_id, event } => {
-                        self.inputs.handle_device_input(device_id, event);
+                        if !self.is_replaying() {
+                            self.inputs.handle_device_input(device_id, event);
+                        }
                     }
-                    Event::UserEvent(_) => {}
-                    Event::Suspended => {}
-                    Event::Resumed => {}
+                    Event::UserEvent(event) => self.handle_user_event(event),
+                    Event::Suspended => self.suspend(),
+                    Event::Resumed => self.resume(),
                     Event::MainEventsCleared => {
-                        self.update();
-                        self.draw();
+                        if !self.graphics.is_suspended() {
+                            self.update();
+                            self.apply_window_commands(event_loop);
+                            self.draw();
+                        }
                     }
                     Event::RedrawRequested(_window_id) => {
                         self.graphics.present();
@@ -149,6 +410,14 @@ impl Application {
         if let Some(ref mut scene) = self.scene {
             scene.force_exit();
         }
+
+        if let (Some(recording), Some(path)) =
+            (self.inputs.stop_recording(), self.recording_path.take())
+        {
+            if let Err(error) = recording.save(&path) {
+                eprintln!("Failed to save inputs to {}: {}", path.display(), error);
+            }
+        }
     }
 
     pub fn exit() {
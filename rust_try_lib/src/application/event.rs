@@ -1,107 +1,292 @@
-use std::any::{Any, TypeId};
+use std::any::TypeId;
 use std::collections::HashMap;
 
 use crate::{utils::BoxedAny, *};
 
-///Literally registers events and correspond listeners.
+use winit::event_loop::{EventLoopClosed, EventLoopProxy};
+
+///Whether later listeners get the event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    Continue,
+    ///Consumes the event, so listeners with lower priority don't get it.
+    Stop,
+}
+
+///Returned by subscribing, to unsubscribe the listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerHandle {
+    event: TypeId,
+    id: u64,
+}
+
+struct Listener {
+    id: u64,
+    priority: i32,
+    ///Box<dyn FnMut(&mut E) -> Propagation + Send> of the event type it's registered under.
+    callback: BoxedAny,
+}
+
+type QueuedEvent = Box<dyn FnOnce(&mut EventBus) + Send>;
+
+///Dispatches events to listeners of their type.
 ///events can be any type but 'static.
-pub struct EventRegistry {
-    pub events: HashMap<TypeId, Vec<BoxedAny>>,
+pub struct EventBus {
+    listeners: HashMap<TypeId, Vec<Listener>>,
+    queue: Vec<QueuedEvent>,
+    next_id: u64,
 }
 
-impl EventRegistry {
-    #[allow(dead_code)]
-    pub(crate) fn new() -> Self {
+impl EventBus {
+    pub fn new() -> Self {
         Self {
-            events: HashMap::new(),
+            listeners: HashMap::new(),
+            queue: Vec::new(),
+            next_id: 0,
         }
     }
 
-    pub fn register<E, F>(&mut self, event: TypeId, listener: F)
+    pub fn subscribe<E, F>(&mut self, listener: F) -> ListenerHandle
     where
         E: 'static,
-        F: 'static + FnMut(&mut E) + Send,
+        F: 'static + FnMut(&mut E) -> Propagation + Send,
     {
-        let listener = BoxedAny::new(box_as!(listener, dyn FnMut(&mut E) + Send));
+        self.subscribe_with_priority(0, listener)
+    }
+
+    ///Higher priority listens first. Same priority listens in subscribed order.
+    pub fn subscribe_with_priority<E, F>(&mut self, priority: i32, listener: F) -> ListenerHandle
+    where
+        E: 'static,
+        F: 'static + FnMut(&mut E) -> Propagation + Send,
+    {
+        let event = TypeId::of::<E>();
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let listeners = self.listeners.entry(event).or_default();
+        let index = listeners
+            .iter()
+            .position(|listener| listener.priority < priority)
+            .unwrap_or(listeners.len());
+        listeners.insert(
+            index,
+            Listener {
+                id,
+                priority,
+                callback: BoxedAny::new(box_as!(listener, dyn FnMut(&mut E) -> Propagation + Send)),
+            },
+        );
+
+        ListenerHandle { event, id }
+    }
 
-        if let Some(vec) = self.events.get_mut(&event) {
-            vec.push(listener);
-        } else {
-            self.events.insert(event, vec![listener]);
+    ///False when it's already unsubscribed.
+    pub fn unsubscribe(&mut self, handle: ListenerHandle) -> bool {
+        if let Some(listeners) = self.listeners.get_mut(&handle.event) {
+            if let Some(index) = listeners
+                .iter()
+                .position(|listener| listener.id == handle.id)
+            {
+                listeners.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn listener_count<E: 'static>(&self) -> usize {
+        self.listeners
+            .get(&TypeId::of::<E>())
+            .map_or(0, |listeners| listeners.len())
+    }
+
+    ///Dispatches right away. True when a listener consumed it.
+    pub fn fire<E: 'static>(&mut self, mut event: E) -> bool {
+        if let Some(listeners) = self.listeners.get_mut(&TypeId::of::<E>()) {
+            for listener in listeners {
+                //Listeners are only registered under their own event type.
+                let callback = listener
+                    .callback
+                    .downcast_mut::<Box<dyn FnMut(&mut E) -> Propagation + Send>>()
+                    .unwrap();
+                if callback(&mut event) == Propagation::Stop {
+                    return true;
+                }
+            }
         }
+        false
+    }
+
+    ///Dispatched at the end of the frame.
+    pub fn queue<E: 'static + Send>(&mut self, event: E) {
+        self.queue.push(Box::new(move |bus: &mut EventBus| {
+            bus.fire(event);
+        }));
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queue.len()
     }
 
-    pub fn fire<E: 'static>(&mut self, mut event: E) {
-        if let Some(listeners) = self.events.get_mut(&event.type_id()) {
-            listeners.iter_mut().for_each(|f| {
-                f.downcast_mut::<Box<dyn FnMut(&mut E) + Send>>().unwrap()(&mut event)
-            });
+    ///Events queued while dispatching wait for the next call.
+    pub fn dispatch_queued(&mut self) {
+        let queue = std::mem::take(&mut self.queue);
+        for dispatch in queue {
+            dispatch(self);
         }
     }
 
+    pub fn clear<E: 'static>(&mut self) {
+        self.listeners.remove(&TypeId::of::<E>());
+    }
+
     pub fn clear_all(&mut self) {
-        self.events.clear();
+        self.listeners.clear();
+        self.queue.clear();
     }
 
     pub fn clear_list(&mut self, list: &[TypeId]) {
         for item in list {
-            self.events.remove(item);
+            self.listeners.remove(item);
         }
     }
 
     pub fn clear_exclude(&mut self, list: &[TypeId]) {
-        let mut temp = Vec::new();
-        for item in list {
-            if let Some(entry) = self.events.remove_entry(item) {
-                temp.push(entry);
-            }
-        }
+        self.listeners.retain(|event, _| list.contains(event));
+    }
+}
 
-        self.events.clear();
+///Event of any type sent from other threads through the event loop.
+///The application gives it to the scene, then fires it to the bus as its own type.
+pub struct UserEvent {
+    event: BoxedAny,
+    fire: fn(BoxedAny, &mut EventBus) -> bool,
+}
 
-        for entry in temp {
-            self.events.insert(entry.0, entry.1);
+impl UserEvent {
+    pub fn new<E: 'static + Send>(event: E) -> Self {
+        Self {
+            event: BoxedAny::new(event),
+            fire: |event, bus| bus.fire(event.downcast::<E>().unwrap()),
         }
     }
+
+    pub fn downcast_ref<E: 'static>(&self) -> Option<&E> {
+        self.event.downcast_ref::<E>()
+    }
+
+    pub fn downcast_mut<E: 'static>(&mut self) -> Option<&mut E> {
+        self.event.downcast_mut::<E>()
+    }
+
+    pub fn into_inner<E: 'static>(self) -> Option<E> {
+        self.event.downcast::<E>()
+    }
+
+    ///True when a listener consumed it.
+    pub fn fire(self, bus: &mut EventBus) -> bool {
+        (self.fire)(self.event, bus)
+    }
+}
+
+///Sends events to the application from any thread, waking up the event loop.
+#[derive(Clone)]
+pub struct EventSender {
+    proxy: EventLoopProxy<UserEvent>,
+}
+
+impl EventSender {
+    pub(crate) fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self { proxy }
+    }
+
+    ///Gives the event back when the application has ended.
+    pub fn send<E: 'static + Send>(&self, event: E) -> Result<(), E> {
+        self.proxy
+            .send_event(UserEvent::new(event))
+            .map_err(|EventLoopClosed(event)| event.into_inner::<E>().unwrap())
+    }
 }
 
 #[cfg(test)]
 mod test_event {
     use super::*;
 
+    use std::sync::{Arc, Mutex};
+
     struct TestEvent {
         a: isize,
     }
 
     struct HiEvent;
 
-    impl TestEvent {
-        fn a(&mut self) {
-            self.a += 1;
-        }
+    #[test]
+    fn priority_and_consumption() {
+        let mut events = EventBus::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let push = |order: &Arc<Mutex<Vec<isize>>>, propagation| {
+            let order = order.clone();
+            move |event: &mut TestEvent| {
+                order.lock().unwrap().push(event.a);
+                event.a += 1;
+                propagation
+            }
+        };
+        let low = events.subscribe(push(&order, Propagation::Continue));
+        events.subscribe_with_priority(10, push(&order, Propagation::Continue));
+        let consumer = events.subscribe_with_priority(5, push(&order, Propagation::Stop));
+        events.subscribe(|_: &mut HiEvent| Propagation::Continue);
+
+        assert!(events.fire(TestEvent { a: 0 }));
+        assert_eq!(*order.lock().unwrap(), vec![0, 1]);
+
+        assert!(events.unsubscribe(consumer));
+        assert!(!events.unsubscribe(consumer));
+        assert!(!events.fire(TestEvent { a: 0 }));
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 0, 1]);
+
+        events.unsubscribe(low);
+        assert_eq!(events.listener_count::<TestEvent>(), 1);
+        assert_eq!(events.listener_count::<HiEvent>(), 1);
     }
 
     #[test]
-    fn it_works() {
-        let mut event_reg = EventRegistry::new();
-        event_reg.register(TypeId::of::<TestEvent>(), TestEvent::a);
-        event_reg.fire(TestEvent { a: 1 });
+    fn queued_at_end_of_frame() {
+        let mut events = EventBus::new();
+        let received = Arc::new(Mutex::new(0));
+
+        let counter = received.clone();
+        events.subscribe(move |event: &mut TestEvent| {
+            *counter.lock().unwrap() += event.a;
+            Propagation::Continue
+        });
+        events.queue(TestEvent { a: 2 });
+        events.queue(TestEvent { a: 3 });
+        assert_eq!(*received.lock().unwrap(), 0);
+
+        events.dispatch_queued();
+        assert_eq!(*received.lock().unwrap(), 5);
+        assert_eq!(events.queued_count(), 0);
     }
 
     #[test]
-    fn is_others_works() {
-        let mut event_reg = EventRegistry::new();
-        assert!(event_reg
-            .events
-            .insert(TypeId::of::<TestEvent>(), Vec::<BoxedAny>::new())
-            .is_none());
-        assert!(event_reg
-            .events
-            .insert(TypeId::of::<TestEvent>(), Vec::<BoxedAny>::new())
-            .is_some());
-        assert!(event_reg
-            .events
-            .insert(TypeId::of::<HiEvent>(), Vec::<BoxedAny>::new())
-            .is_none());
+    fn user_event_fires_as_its_type() {
+        let mut events = EventBus::new();
+        let received = Arc::new(Mutex::new(0));
+
+        let counter = received.clone();
+        events.subscribe(move |event: &mut TestEvent| {
+            *counter.lock().unwrap() = event.a;
+            Propagation::Stop
+        });
+
+        let mut user_event = UserEvent::new(TestEvent { a: 1 });
+        assert!(user_event.downcast_ref::<HiEvent>().is_none());
+        user_event.downcast_mut::<TestEvent>().unwrap().a = 7;
+        assert!(user_event.fire(&mut events));
+        assert_eq!(*received.lock().unwrap(), 7);
+        assert!(!UserEvent::new(HiEvent).fire(&mut events));
     }
 }
@@ -1,4 +1,9 @@
-use crate::{graphics::Graphics, inputs::Inputs, utils::Utils};
+use crate::{
+    application::{EventBus, UserEvent, WindowCommands},
+    graphics::{Graphics, GraphicsError, WindowConfig},
+    inputs::Inputs,
+    utils::Utils,
+};
 
 use winit::window::WindowId;
 
@@ -7,7 +12,49 @@ pub trait Scene: Send {
 
     fn resize(&mut self, window_id: WindowId, new_size: winit::dpi::PhysicalSize<u32>);
 
-    fn update(&mut self, utils: &Utils, inputs: &Inputs);
+    ///Events queued to the bus are dispatched, and window commands are executed after update.
+    fn update(
+        &mut self,
+        utils: &mut Utils,
+        inputs: &Inputs,
+        events: &mut EventBus,
+        windows: &mut WindowCommands,
+    );
+
+    fn focus(&mut self, _window_id: WindowId, _focused: bool) {}
+
+    ///Surfaces are gone while suspended, so nothing is updated or rendered.
+    fn suspend(&mut self) {}
+
+    ///Surfaces are recreated before this. Only called after suspend.
+    fn resume(&mut self) {}
+
+    ///Return false to keep the window, e.g. to ask about unsaved changes first.
+    ///Application::exit still closes every window.
+    fn close_requested(&mut self, _window_id: WindowId) -> bool {
+        true
+    }
+
+    ///Opened by WindowCommands. Renderers for the window can be made with the graphics.
+    fn window_opened(&mut self, _window_id: WindowId, _graphics: &Graphics) {}
+
+    ///Window asked by WindowCommands couldn't be opened.
+    fn window_failed(&mut self, _config: &WindowConfig, _error: &GraphicsError) {}
+
+    ///Closed by WindowCommands or by the user.
+    fn window_closed(&mut self, _window_id: WindowId) {}
+
+    ///Cursor mode or icon set by Inputs couldn't be applied. Cursor keeps the previous ones.
+    fn cursor_failed(&mut self, _window_id: WindowId, _error: &GraphicsError) {}
+
+    ///Sent through EventSender. It's fired to the bus after this.
+    fn user_event(&mut self, _event: &mut UserEvent) {}
+
+    ///Error of the graphics which won't go away by itself, e.g. out of memory.
+    ///Return false to keep running. Application exits otherwise.
+    fn graphics_failed(&mut self, _error: &GraphicsError) -> bool {
+        true
+    }
 
     fn render(&mut self, graphics: &Graphics);
 
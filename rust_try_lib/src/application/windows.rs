@@ -0,0 +1,36 @@
+use crate::graphics::WindowConfig;
+
+use winit::window::WindowId;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum WindowCommand {
+    Create(WindowConfig),
+    Close(WindowId),
+}
+
+///Scenes ask the application to open and close windows with this.
+///Commands are executed after update, and the scene is told by window_opened, window_failed and window_closed.
+pub struct WindowCommands {
+    commands: Vec<WindowCommand>,
+}
+
+impl WindowCommands {
+    pub(crate) fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn create_window(&mut self, config: WindowConfig) {
+        self.commands.push(WindowCommand::Create(config));
+    }
+
+    ///Closes without asking Scene::close_requested.
+    pub fn close_window(&mut self, window_id: WindowId) {
+        self.commands.push(WindowCommand::Close(window_id));
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<WindowCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
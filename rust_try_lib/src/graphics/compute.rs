@@ -0,0 +1,324 @@
+//!Compute works outside of render passes. Works with headless graphics too.
+use super::{error::*, graphics::*};
+
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeBufferUsage {
+    ///Readable and writable from shader. Can be read back to cpu and bound as vertex buffer.
+    Storage,
+    ///Can be read back to cpu.
+    Uniform,
+}
+
+impl ComputeBufferUsage {
+    fn to_wgpu(self) -> wgpu::BufferUsages {
+        match self {
+            ComputeBufferUsage::Storage => {
+                wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST
+            }
+            ComputeBufferUsage::Uniform => {
+                wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST
+            }
+        }
+    }
+}
+
+pub struct ComputeBuffer {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    usage: ComputeBufferUsage,
+
+    graphics_core: Arc<GraphicsCore>,
+}
+
+impl ComputeBuffer {
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.size
+    }
+
+    pub fn usage(&self) -> ComputeBufferUsage {
+        self.usage
+    }
+
+    pub fn raw(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn write<T: bytemuck::Pod>(&self, offset: wgpu::BufferAddress, data: &[T]) {
+        self.graphics_core
+            .queue
+            .write_buffer(&self.buffer, offset, bytemuck::cast_slice(data));
+    }
+
+    ///Copies whole buffer to cpu after all submitted works.
+    ///Waits on the device for the copy, so it's done by the first poll.
+    pub async fn read<T: bytemuck::Pod>(&self) -> Result<Vec<T>, GraphicsError> {
+        let staging_buffer = self.copy_to_staging()?;
+
+        let mapping = staging_buffer.slice(..).map_async(wgpu::MapMode::Read);
+        self.graphics_core.device.poll(wgpu::Maintain::Wait);
+        mapping.await?;
+
+        Ok(Self::take_mapped(&staging_buffer))
+    }
+
+    ///Blocks the thread until the copy is done.
+    pub fn read_blocking<T: bytemuck::Pod>(&self) -> Result<Vec<T>, GraphicsError> {
+        pollster::block_on(self.read())
+    }
+
+    ///Err when size can't be copied, which wgpu would panic at.
+    fn copy_to_staging(&self) -> Result<wgpu::Buffer, GraphicsError> {
+        if self.size == 0 || !self.size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+            return Err(GraphicsError::InvalidCopySize(self.size));
+        }
+        let device = &self.graphics_core.device;
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Staging Buffer"),
+            size: self.size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Compute Read Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &staging_buffer, 0, self.size);
+        self.graphics_core
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+
+        Ok(staging_buffer)
+    }
+
+    fn take_mapped<T: bytemuck::Pod>(staging_buffer: &wgpu::Buffer) -> Vec<T> {
+        let data = bytemuck::cast_slice(&staging_buffer.slice(..).get_mapped_range()).to_vec();
+        staging_buffer.unmap();
+        data
+    }
+}
+
+pub enum ComputeResource<'a> {
+    Buffer(&'a ComputeBuffer),
+    TextureView(&'a wgpu::TextureView),
+    Sampler(&'a wgpu::Sampler),
+}
+
+pub struct ComputeBindGroup {
+    group: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ComputeBindGroup {
+    pub fn group(&self) -> u32 {
+        self.group
+    }
+}
+
+///Bind group layouts are derived from shader.
+pub struct ComputePipeline {
+    pipeline: wgpu::ComputePipeline,
+
+    graphics_core: Arc<GraphicsCore>,
+}
+
+impl ComputePipeline {
+    ///entries are pairs of binding and resource.
+    pub fn bind(&self, group: u32, entries: &[(u32, ComputeResource)]) -> ComputeBindGroup {
+        let bind_group = self
+            .graphics_core
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Compute Bind Group"),
+                layout: &self.pipeline.get_bind_group_layout(group),
+                entries: &entries
+                    .iter()
+                    .map(|(binding, resource)| wgpu::BindGroupEntry {
+                        binding: *binding,
+                        resource: match resource {
+                            ComputeResource::Buffer(buffer) => buffer.buffer.as_entire_binding(),
+                            ComputeResource::TextureView(view) => {
+                                wgpu::BindingResource::TextureView(view)
+                            }
+                            ComputeResource::Sampler(sampler) => {
+                                wgpu::BindingResource::Sampler(sampler)
+                            }
+                        },
+                    })
+                    .collect::<Vec<_>>(),
+            });
+
+        ComputeBindGroup { group, bind_group }
+    }
+
+    ///Submits a dispatch immediately.
+    pub fn dispatch(&self, bind_groups: &[&ComputeBindGroup], workgroups: [u32; 3]) {
+        let mut encoder =
+            self.graphics_core
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Compute Encoder"),
+                });
+        self.record(&mut encoder, bind_groups, workgroups);
+        self.graphics_core
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+    }
+
+    ///Records a dispatch into encoder, so it can be ordered with other passes.
+    pub fn record(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_groups: &[&ComputeBindGroup],
+        workgroups: [u32; 3],
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        for bind_group in bind_groups {
+            compute_pass.set_bind_group(bind_group.group, &bind_group.bind_group, &[]);
+        }
+        compute_pass.dispatch(workgroups[0], workgroups[1], workgroups[2]);
+    }
+}
+
+impl Graphics {
+    pub fn create_compute_pipeline(
+        &self,
+        name: &str,
+        source: &str,
+        entry_point: &str,
+    ) -> ComputePipeline {
+        let device = &self.core.device;
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some(&(name.to_string() + " Shader Module")),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&(name.to_string() + " Compute Pipeline")),
+            layout: None,
+            module: &shader,
+            entry_point,
+        });
+
+        ComputePipeline {
+            pipeline,
+            graphics_core: self.core.clone(),
+        }
+    }
+
+    pub fn create_compute_buffer<T: bytemuck::Pod>(
+        &self,
+        name: &str,
+        usage: ComputeBufferUsage,
+        contents: &[T],
+    ) -> ComputeBuffer {
+        let contents = bytemuck::cast_slice(contents);
+        let buffer = self
+            .core
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&(name.to_string() + " Buffer")),
+                contents,
+                usage: usage.to_wgpu(),
+            });
+
+        ComputeBuffer {
+            buffer,
+            size: contents.len() as wgpu::BufferAddress,
+            usage,
+            graphics_core: self.core.clone(),
+        }
+    }
+
+    pub fn create_compute_buffer_zeroed(
+        &self,
+        name: &str,
+        usage: ComputeBufferUsage,
+        size: wgpu::BufferAddress,
+    ) -> ComputeBuffer {
+        let buffer = self.core.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&(name.to_string() + " Buffer")),
+            size,
+            usage: usage.to_wgpu(),
+            mapped_at_creation: false,
+        });
+
+        ComputeBuffer {
+            buffer,
+            size,
+            usage,
+            graphics_core: self.core.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a GPU adapter, run with --ignored"]
+    fn doubles_in_headless() {
+        let graphics = Graphics::headless_for_test("Compute Test");
+
+        let pipeline = graphics.create_compute_pipeline(
+            "Double",
+            "
+            struct Values { data: array<f32>; };
+            [[group(0), binding(0)]]
+            var<storage, read_write> values: Values;
+
+            [[stage(compute), workgroup_size(4)]]
+            fn main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+                values.data[id.x] = values.data[id.x] * 2.0;
+            }
+            ",
+            "main",
+        );
+        let buffer = graphics.create_compute_buffer(
+            "Values",
+            ComputeBufferUsage::Storage,
+            &[1.0f32, 2.0, 3.0, 4.0],
+        );
+        let bind_group = pipeline.bind(0, &[(0, ComputeResource::Buffer(&buffer))]);
+        pipeline.dispatch(&[&bind_group], [1, 1, 1]);
+
+        assert_eq!(
+            buffer.read_blocking::<f32>().unwrap(),
+            vec![2.0, 4.0, 6.0, 8.0]
+        );
+        assert_eq!(
+            pollster::block_on(buffer.read::<f32>()).unwrap(),
+            vec![2.0, 4.0, 6.0, 8.0]
+        );
+
+        let uniform =
+            graphics.create_compute_buffer("Uniform", ComputeBufferUsage::Uniform, &[7u32]);
+        assert_eq!(uniform.read_blocking::<u32>().unwrap(), vec![7]);
+
+        let empty = graphics.create_compute_buffer_zeroed("Empty", ComputeBufferUsage::Storage, 0);
+        assert!(matches!(
+            empty.read_blocking::<u32>(),
+            Err(GraphicsError::InvalidCopySize(0))
+        ));
+        let unaligned =
+            graphics.create_compute_buffer("Unaligned", ComputeBufferUsage::Storage, &[1u8; 3]);
+        assert!(matches!(
+            unaligned.read_blocking::<u8>(),
+            Err(GraphicsError::InvalidCopySize(3))
+        ));
+    }
+}
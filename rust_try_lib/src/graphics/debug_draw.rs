@@ -0,0 +1,448 @@
+//!Immediate mode line drawing for debugging. Lines are cleared every render.
+use super::{elements::*, error::*, graphics::*};
+
+use std::sync::Arc;
+
+use winit::window::WindowId;
+
+use cgmath::*;
+
+///Collects line list vertices. Every two vertices make a line.
+#[derive(Default)]
+pub struct DebugLines {
+    vertices: Vec<ColorVertex>,
+}
+
+impl DebugLines {
+    pub const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    pub const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+    pub const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn vertices(&self) -> &[ColorVertex] {
+        &self.vertices
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 4]) {
+        self.vertices
+            .push(ColorVertex::new(from.to_homogeneous().into(), color));
+        self.vertices
+            .push(ColorVertex::new(to.to_homogeneous().into(), color));
+    }
+
+    pub fn ray(&mut self, origin: Point3<f32>, direction: Vector3<f32>, color: [f32; 4]) {
+        self.line(origin, origin + direction, color);
+    }
+
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: [f32; 4]) {
+        let corner = |i: usize| {
+            point3(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        self.box_edges(corner, color);
+    }
+
+    ///Three great circles on each axis plane.
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 4]) {
+        let axes = [
+            (Vector3::unit_x(), Vector3::unit_y()),
+            (Vector3::unit_y(), Vector3::unit_z()),
+            (Vector3::unit_z(), Vector3::unit_x()),
+        ];
+        for (u, v) in axes {
+            let point = |i: usize| {
+                let angle = Rad::full_turn() * (i as f32 / Self::CIRCLE_SEGMENTS as f32);
+                center + (u * angle.cos() + v * angle.sin()) * radius
+            };
+            for i in 0..Self::CIRCLE_SEGMENTS {
+                self.line(point(i), point(i + 1), color);
+            }
+        }
+    }
+
+    ///Square grid on xz plane.
+    pub fn grid(&mut self, center: Point3<f32>, size: f32, divisions: u32, color: [f32; 4]) {
+        let divisions = divisions.max(1);
+        let half = size * 0.5;
+        for i in 0..=divisions {
+            let offset = -half + size * (i as f32 / divisions as f32);
+            self.line(
+                center + vec3(offset, 0.0, -half),
+                center + vec3(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + vec3(-half, 0.0, offset),
+                center + vec3(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    ///x, y, z axes of transform as red, green, blue.
+    pub fn axes(&mut self, transform_matrix: Matrix4<f32>, length: f32) {
+        let origin = Point3::from_homogeneous(transform_matrix.w);
+        for (axis, color) in [
+            (transform_matrix.x, Self::RED),
+            (transform_matrix.y, Self::GREEN),
+            (transform_matrix.z, Self::BLUE),
+        ] {
+            self.ray(origin, axis.truncate() * length, color);
+        }
+    }
+
+    ///Edges of the volume which view_proj sees. Expects wgpu clip space(z in 0..1).
+    pub fn frustum(&mut self, view_proj: Matrix4<f32>, color: [f32; 4]) {
+        let inverse = match view_proj.invert() {
+            Some(inverse) => inverse,
+            _ => return,
+        };
+        let corner = |i: usize| {
+            Point3::from_homogeneous(
+                inverse
+                    * vec4(
+                        if i & 1 == 0 { -1.0 } else { 1.0 },
+                        if i & 2 == 0 { -1.0 } else { 1.0 },
+                        if i & 4 == 0 { 0.0 } else { 1.0 },
+                        1.0,
+                    ),
+            )
+        };
+        self.box_edges(corner, color);
+    }
+
+    ///corner gets index which bits are x, y, z sides.
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Point3<f32>, color: [f32; 4]) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+}
+
+///Draws DebugLines with LineList pipeline after main pass.
+pub struct DebugDraw {
+    lines: DebugLines,
+    depth_test: bool,
+
+    depth_tested_pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+    view_proj_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+
+    graphics_core: Arc<GraphicsCore>,
+}
+
+impl DebugDraw {
+    const INITIAL_VERTEX_CAPACITY: usize = 1024;
+
+    pub fn new(graphics: &Graphics, target_window_id: WindowId) -> Self {
+        let format = graphics
+            .window_surface(target_window_id)
+            .expect("Target window doesn't exist")
+            .surface_config
+            .format;
+        Self::with_format(graphics, format)
+    }
+
+    pub(crate) fn with_format(graphics: &Graphics, format: wgpu::TextureFormat) -> Self {
+        let device = &graphics.core.device;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Debug Draw Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Draw Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&include_wgsl!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/shaders/debug_line.wgsl"
+        )));
+
+        let create_pipeline = |depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Debug Draw Render Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[ColorVertex::buffer_layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let depth_tested_pipeline = create_pipeline(wgpu::CompareFunction::LessEqual);
+        let overlay_pipeline = create_pipeline(wgpu::CompareFunction::Always);
+
+        let view_proj_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Draw View Projection Buffer"),
+            size: std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Debug Draw Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_proj_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            lines: DebugLines::new(),
+            depth_test: true,
+
+            depth_tested_pipeline,
+            overlay_pipeline,
+            view_proj_buffer,
+            bind_group,
+
+            vertex_buffer: Self::create_vertex_buffer(device, Self::INITIAL_VERTEX_CAPACITY),
+            vertex_capacity: Self::INITIAL_VERTEX_CAPACITY,
+
+            graphics_core: graphics.core.clone(),
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Draw Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<ColorVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    ///When false, lines are drawn over everything.
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+}
+
+impl DebugDraw {
+    ///Draws and clears batched lines.
+    ///They are cleared on error as well, so they don't pile up while the window is minimized.
+    pub fn render(
+        &mut self,
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        view_proj: Matrix4<f32>,
+    ) -> Result<(), GraphicsError> {
+        if self.lines.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.draw(graphics, target_window_id, view_proj);
+        self.lines.clear();
+        result
+    }
+
+    fn draw(
+        &mut self,
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        view_proj: Matrix4<f32>,
+    ) -> Result<(), GraphicsError> {
+        let (window_surface, surface_texture_view) =
+            graphics.surface_texture_view(target_window_id)?;
+
+        let vertex_count = self.lines.vertices.len();
+        if vertex_count > self.vertex_capacity {
+            self.vertex_capacity = vertex_count.next_power_of_two();
+            self.vertex_buffer =
+                Self::create_vertex_buffer(&self.graphics_core.device, self.vertex_capacity);
+        }
+
+        let queue = &self.graphics_core.queue;
+        queue.write_buffer(
+            &self.view_proj_buffer,
+            0,
+            bytemuck::cast_slice(AsRef::<[[f32; 4]; 4]>::as_ref(&view_proj)),
+        );
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.lines.vertices),
+        );
+
+        let mut encoder =
+            self.graphics_core
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Debug Draw Encoder"),
+                });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug Draw Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: surface_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &window_surface.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(if self.depth_test {
+                &self.depth_tested_pipeline
+            } else {
+                &self.overlay_pipeline
+            });
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..vertex_count as u32, 0..1);
+        }
+
+        self.graphics_core
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for DebugDraw {
+    type Target = DebugLines;
+
+    fn deref(&self) -> &Self::Target {
+        &self.lines
+    }
+}
+
+impl std::ops::DerefMut for DebugDraw {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shapes_line_count() {
+        let mut lines = DebugLines::new();
+        lines.aabb(
+            point3(0.0, 0.0, 0.0),
+            point3(1.0, 1.0, 1.0),
+            DebugLines::RED,
+        );
+        assert_eq!(lines.line_count(), 12);
+
+        lines.clear();
+        lines.axes(Matrix4::identity(), 1.0);
+        assert_eq!(lines.line_count(), 3);
+        assert_eq!(lines.vertices()[1].position, [1.0, 0.0, 0.0, 1.0]);
+
+        lines.clear();
+        lines.grid(point3(0.0, 0.0, 0.0), 2.0, 4, DebugLines::GREEN);
+        assert_eq!(lines.line_count(), 10);
+    }
+
+    #[test]
+    fn frustum_of_identity_is_clip_box() {
+        let mut lines = DebugLines::new();
+        lines.frustum(Matrix4::identity(), DebugLines::BLUE);
+        assert_eq!(lines.line_count(), 12);
+        assert!(lines.vertices().iter().all(|vertex| {
+            let [x, y, z, _] = vertex.position;
+            x.abs() == 1.0 && y.abs() == 1.0 && (z == 0.0 || z == 1.0)
+        }));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter, run with --ignored"]
+    fn cleared_on_error() {
+        let graphics = Graphics::headless_for_test("Debug Draw Test");
+        let mut debug_draw = DebugDraw::with_format(&graphics, wgpu::TextureFormat::Bgra8UnormSrgb);
+
+        //No surface for the window, as if it's minimized.
+        let window_id = unsafe { WindowId::dummy() };
+        debug_draw.axes(Matrix4::identity(), 1.0);
+        assert!(debug_draw
+            .render(&graphics, window_id, Matrix4::identity())
+            .is_err());
+        assert_eq!(debug_draw.line_count(), 0);
+    }
+}
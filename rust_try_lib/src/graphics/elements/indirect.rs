@@ -0,0 +1,167 @@
+//!Gpu side layouts for indirect drawing. Must match cull_instances.wgsl
+
+///Same layout as arguments of draw_indexed_indirect.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndexedIndirect {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+impl DrawIndexedIndirect {
+    pub const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+}
+
+///Instance before culling. slot points which mesh it belongs to.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CullInstance {
+    pub transform_matrix: [[f32; 4]; 4],
+    pub slot: u32,
+    _padding: [u32; 3],
+}
+
+impl CullInstance {
+    pub fn new(transform_matrix: [[f32; 4]; 4], slot: u32) -> Self {
+        Self {
+            transform_matrix,
+            slot,
+            _padding: [0; 3],
+        }
+    }
+}
+
+///Per mesh data for culling. base is where visible instances of the mesh start.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshSlot {
+    pub bounds: [f32; 4],
+    pub base: u32,
+    _padding: [u32; 3],
+}
+
+impl MeshSlot {
+    pub fn new(bounds: [f32; 4], base: u32) -> Self {
+        Self {
+            bounds,
+            base,
+            _padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Frustum {
+    pub planes: [[f32; 4]; 6],
+    pub instance_count: u32,
+    _padding: [u32; 3],
+}
+
+impl Frustum {
+    ///Extracts planes from view projection matrix. Expects wgpu clip space(z in 0..1).
+    pub fn new(view_proj: cgmath::Matrix4<f32>, instance_count: u32) -> Self {
+        use cgmath::Matrix;
+
+        let r = [
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        ];
+        let mut planes = [
+            r[3] + r[0],
+            r[3] - r[0],
+            r[3] + r[1],
+            r[3] - r[1],
+            r[2],
+            r[3] - r[2],
+        ];
+        for plane in planes.iter_mut() {
+            let length = (plane.x * plane.x + plane.y * plane.y + plane.z * plane.z).sqrt();
+            if length > f32::EPSILON {
+                *plane /= length;
+            }
+        }
+
+        Self {
+            planes: planes.map(Into::into),
+            instance_count,
+            _padding: [0; 3],
+        }
+    }
+
+    ///Planes which never cull anything.
+    pub fn everything(instance_count: u32) -> Self {
+        Self {
+            planes: [[0.0, 0.0, 0.0, 1.0]; 6],
+            instance_count,
+            _padding: [0; 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cgmath::*;
+
+    fn distances(frustum: &Frustum, point: Point3<f32>) -> Vec<f32> {
+        frustum
+            .planes
+            .iter()
+            .map(|plane| Vector4::from(*plane).dot(point.to_homogeneous()))
+            .collect()
+    }
+
+    #[test]
+    fn planes_of_perspective() {
+        //z from -1..1 of OpenGL to 0..1 of wgpu.
+        #[rustfmt::skip]
+        let to_wgpu = Matrix4::new(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.5, 0.0,
+            0.0, 0.0, 0.5, 1.0,
+        );
+        let view_proj = to_wgpu * perspective(Deg(90.0), 1.0, 1.0, 10.0);
+        let frustum = Frustum::new(view_proj, 3);
+        assert_eq!(frustum.instance_count, 3);
+
+        assert!(distances(&frustum, point3(0.0, 0.0, -5.0))
+            .iter()
+            .all(|distance| *distance > 0.0));
+
+        //Left, right, bottom, top, near and far in order.
+        let outside = [
+            point3(-6.0, 0.0, -5.0),
+            point3(6.0, 0.0, -5.0),
+            point3(0.0, -6.0, -5.0),
+            point3(0.0, 6.0, -5.0),
+            point3(0.0, 0.0, -0.5),
+            point3(0.0, 0.0, -11.0),
+        ];
+        for (plane, point) in outside.iter().enumerate() {
+            let distances = distances(&frustum, *point);
+            for (index, distance) in distances.iter().enumerate() {
+                assert_eq!(
+                    *distance < 0.0,
+                    index == plane,
+                    "{:?} {:?}",
+                    point,
+                    distances
+                );
+            }
+        }
+
+        //Normalized, so distances are in world units.
+        assert!((distances(&frustum, point3(0.0, 0.0, -0.5))[4] + 0.5).abs() < 1e-4);
+        assert!(distances(&Frustum::everything(0), point3(1e6, -1e6, 1e6))
+            .iter()
+            .all(|distance| *distance > 0.0));
+    }
+}
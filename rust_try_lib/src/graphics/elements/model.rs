@@ -36,6 +36,45 @@ impl Mesh {
         &self.indices
     }
 
+    ///Bounding sphere in model space. xyz for center, w for radius.
+    pub fn bounds(&self) -> [f32; 4] {
+        let positions = self.vertices.iter().map(|vertex| {
+            let [x, y, z, w] = vertex.position;
+            let w = if w.abs() > f32::EPSILON { w } else { 1.0 };
+            [x / w, y / w, z / w]
+        });
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for position in positions.clone() {
+            for i in 0..3 {
+                min[i] = min[i].min(position[i]);
+                max[i] = max[i].max(position[i]);
+            }
+        }
+        if self.vertices.is_empty() {
+            return [0.0; 4];
+        }
+
+        let center = [
+            (min[0] + max[0]) * 0.5,
+            (min[1] + max[1]) * 0.5,
+            (min[2] + max[2]) * 0.5,
+        ];
+        let radius = positions
+            .map(|position| {
+                let (x, y, z) = (
+                    position[0] - center[0],
+                    position[1] - center[1],
+                    position[2] - center[2],
+                );
+                (x * x + y * y + z * z).sqrt()
+            })
+            .fold(0.0, f32::max);
+
+        [center[0], center[1], center[2], radius]
+    }
+
     pub fn to_buffer(&self, device: &wgpu::Device) -> MeshBuffer {
         MeshBuffer::new(device, self.id, &self.vertices, &self.indices)
     }
@@ -1,5 +1,7 @@
 // use crate::graphics::GraphicsCore;
 
+use std::{convert::TryInto, num::NonZeroU32};
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -8,6 +10,9 @@ pub struct Texture {
 
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    pub const CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    ///Filterable, unlike 32 bit floats.
+    pub const HDR_CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
     pub fn create_depth_texture(
         device: &wgpu::Device,
@@ -49,6 +54,307 @@ impl Texture {
             sampler,
         }
     }
+
+    ///faces are ordered as +X, -X, +Y, -Y, +Z, -Z. All faces should have same square size.
+    ///Err when they don't, or when they are empty or larger than the device allows.
+    pub fn create_cube_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::RgbaImage; 6],
+        label: &str,
+    ) -> Result<Self, image::error::ImageError> {
+        let face_size = validate_cube_faces(faces, device.limits().max_texture_dimension_2d)?;
+        Ok(Self::create_cube(
+            device,
+            queue,
+            Self::CUBE_FORMAT,
+            face_size,
+            faces.iter().map(|face| face.as_raw().clone()),
+            label,
+        ))
+    }
+
+    ///Same as create_cube_texture, but keeps values over 1 in HDR_CUBE_FORMAT.
+    pub fn create_hdr_cube_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[HdrImage; 6],
+        label: &str,
+    ) -> Result<Self, image::error::ImageError> {
+        let face_size = validate_cube_faces(faces, device.limits().max_texture_dimension_2d)?;
+        let to_half = |face: &HdrImage| {
+            let half: Vec<u16> = face
+                .as_raw()
+                .iter()
+                .map(|value| half::f16::from_f32(*value).to_bits())
+                .collect();
+            bytemuck::cast_slice(&half).to_vec()
+        };
+        Ok(Self::create_cube(
+            device,
+            queue,
+            Self::HDR_CUBE_FORMAT,
+            face_size,
+            faces.iter().map(to_half),
+            label,
+        ))
+    }
+
+    fn create_cube(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        face_size: u32,
+        faces: impl Iterator<Item = Vec<u8>>,
+        label: &str,
+    ) -> Self {
+        let (width, height) = (face_size, face_size);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        let bytes_per_pixel = format.describe().block_size as u32;
+        for (layer, face) in faces.enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &face,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_pixel * width),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(label),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    ///Six encoded images(png, jpeg). Ordered as +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cube_from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: [&[u8]; 6],
+        label: &str,
+    ) -> Result<Self, image::error::ImageError> {
+        let mut images = Vec::with_capacity(CUBE_FACE_COUNT);
+        for face in faces {
+            let image = image::load_from_memory(face)?.to_rgba8();
+            let size = images
+                .first()
+                .map(image::RgbaImage::width)
+                .unwrap_or_else(|| image.width().min(image.height()));
+            images.push(if image.dimensions() == (size, size) {
+                image
+            } else {
+                image::imageops::resize(&image, size, size, image::imageops::FilterType::Triangle)
+            });
+        }
+
+        let images: [image::RgbaImage; CUBE_FACE_COUNT] = match images.try_into() {
+            Ok(images) => images,
+            Err(_) => unreachable!("Always six faces"),
+        };
+        Self::create_cube_texture(device, queue, &images, label)
+    }
+
+    ///Single encoded equirectangular(latitude-longitude) image, projected to faces of face_size.
+    pub fn cube_from_equirectangular_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        face_size: u32,
+        label: &str,
+    ) -> Result<Self, image::error::ImageError> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        Self::create_cube_texture(
+            device,
+            queue,
+            &equirectangular_to_cube(&image, face_size),
+            label,
+        )
+    }
+
+    ///Single Radiance HDR(.hdr) equirectangular image, projected to faces of face_size.
+    pub fn cube_from_equirectangular_hdr_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        face_size: u32,
+        label: &str,
+    ) -> Result<Self, image::error::ImageError> {
+        Self::create_hdr_cube_texture(
+            device,
+            queue,
+            &equirectangular_to_cube(&decode_hdr(bytes)?, face_size),
+            label,
+        )
+    }
+}
+
+///Linear colors, which can go over 1.
+pub type HdrImage = image::ImageBuffer<image::Rgba<f32>, Vec<f32>>;
+
+///Radiance HDR(.hdr) image, with alpha of 1.
+pub fn decode_hdr(bytes: &[u8]) -> Result<HdrImage, image::error::ImageError> {
+    let decoder = image::codecs::hdr::HdrDecoder::new(std::io::Cursor::new(bytes))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+    Ok(HdrImage::from_fn(metadata.width, metadata.height, |x, y| {
+        let image::Rgb([r, g, b]) = pixels[(y * metadata.width + x) as usize];
+        image::Rgba([r, g, b, 1.0])
+    }))
+}
+
+pub const CUBE_FACE_COUNT: usize = 6;
+
+///Size of the faces when all are the same square, not empty and within max_dimension.
+pub fn validate_cube_faces<P: image::Pixel + 'static>(
+    faces: &[image::ImageBuffer<P, Vec<P::Subpixel>>; CUBE_FACE_COUNT],
+    max_dimension: u32,
+) -> Result<u32, image::error::ImageError> {
+    use image::error::{
+        ImageError, LimitError, LimitErrorKind, ParameterError, ParameterErrorKind,
+    };
+
+    let (size, _) = faces[0].dimensions();
+    if faces.iter().any(|face| face.dimensions() != (size, size)) {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+    if size == 0 || size > max_dimension {
+        return Err(ImageError::Limits(LimitError::from_kind(
+            LimitErrorKind::DimensionError,
+        )));
+    }
+    Ok(size)
+}
+
+///Direction of cube face pixel. u, v in -1..1 from top left.
+pub fn cube_face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    let direction = match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    };
+    let length =
+        (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2])
+            .sqrt();
+    [
+        direction[0] / length,
+        direction[1] / length,
+        direction[2] / length,
+    ]
+}
+
+pub fn equirectangular_to_cube<P: image::Pixel + 'static>(
+    image: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    face_size: u32,
+) -> [image::ImageBuffer<P, Vec<P::Subpixel>>; CUBE_FACE_COUNT] {
+    let (width, height) = image.dimensions();
+    let face = |face| {
+        image::ImageBuffer::from_fn(face_size, face_size, |x, y| {
+            let u = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+            let v = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+            let [dx, dy, dz] = cube_face_direction(face, u, v);
+
+            let longitude = dz.atan2(dx);
+            let latitude = dy.clamp(-1.0, 1.0).acos();
+            let s = longitude / (2.0 * std::f32::consts::PI) + 0.5;
+            let t = latitude / std::f32::consts::PI;
+
+            let px = ((s * width as f32) as u32).min(width - 1);
+            let py = ((t * height as f32) as u32).min(height - 1);
+            *image.get_pixel(px, py)
+        })
+    };
+    [face(0), face(1), face(2), face(3), face(4), face(5)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn equirectangular_poles() {
+        let image = image::RgbaImage::from_fn(8, 4, |_, y| {
+            if y < 2 {
+                image::Rgba([0, 255, 0, 255])
+            } else {
+                image::Rgba([255, 0, 0, 255])
+            }
+        });
+        let faces = equirectangular_to_cube(&image, 4);
+
+        assert_eq!(*faces[2].get_pixel(2, 2), image::Rgba([0, 255, 0, 255]));
+        assert_eq!(*faces[3].get_pixel(2, 2), image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn invalid_cube_faces() {
+        let square = || image::RgbaImage::new(4, 4);
+        let faces = [square(), square(), square(), square(), square(), square()];
+        assert_eq!(validate_cube_faces(&faces, 8).unwrap(), 4);
+        assert!(validate_cube_faces(&faces, 2).is_err());
+
+        let mut mismatched = faces.clone();
+        mismatched[5] = image::RgbaImage::new(8, 8);
+        assert!(validate_cube_faces(&mismatched, 8).is_err());
+
+        let mut not_square = faces.clone();
+        not_square[0] = image::RgbaImage::new(4, 2);
+        assert!(validate_cube_faces(&not_square, 8).is_err());
+
+        let empty = equirectangular_to_cube(&image::RgbaImage::new(8, 4), 0);
+        assert!(validate_cube_faces(&empty, 8).is_err());
+    }
 }
 
 // pub struct TextureConfig {
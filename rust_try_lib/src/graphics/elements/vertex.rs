@@ -1,12 +1,13 @@
-use std::mem;
-
 use cgmath::*;
 
+///Derived with #[location(n)], #[format(..)] and #[step_mode(..)] attributes.
+pub use rust_try_derive::Vertex;
+
 pub trait Vertex {
     fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a>;
 }
 
-#[derive(Clone, Debug, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Clone, Debug, Copy, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
 #[repr(C)]
 pub struct ColorVertex {
     pub position: [f32; 4],
@@ -19,30 +20,11 @@ impl ColorVertex {
     }
 }
 
-impl Vertex for ColorVertex {
-    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                },
-            ],
-        }
-    }
-}
-
 #[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[step_mode(Instance)]
 pub struct Instance {
+    #[location(2)]
     transform_matrix: [[f32; 4]; 4],
 }
 
@@ -61,6 +43,10 @@ impl Instance {
             transform_matrix: transform_matrix.into(),
         }
     }
+
+    pub fn transform_matrix(&self) -> [[f32; 4]; 4] {
+        self.transform_matrix
+    }
 }
 
 impl From<Point3<f32>> for Instance {
@@ -71,33 +57,40 @@ impl From<Point3<f32>> for Instance {
     }
 }
 
-impl Vertex for Instance {
-    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<Instance>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn derived_layouts() {
+        let layout = Instance::buffer_layout();
+        assert_eq!(layout.array_stride, 64);
+        assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+        let attributes: Vec<(u64, u32)> = layout
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.offset, attribute.shader_location))
+            .collect();
+        assert_eq!(attributes, vec![(0, 2), (16, 3), (32, 4), (48, 5)]);
+
+        let layout = ColorVertex::buffer_layout();
+        assert_eq!(layout.step_mode, wgpu::VertexStepMode::Vertex);
+        assert_eq!(
+            layout.attributes,
+            &[
                 wgpu::VertexAttribute {
                     format: wgpu::VertexFormat::Float32x4,
                     offset: 0,
-                    shader_location: 2,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                    shader_location: 3,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 4,
+                    shader_location: 0,
                 },
                 wgpu::VertexAttribute {
                     format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
-                    shader_location: 5,
+                    offset: 16,
+                    shader_location: 1,
                 },
-            ],
-        }
+            ]
+        );
+        assert_eq!(offset_of!(ColorVertex, color), 16);
     }
 }
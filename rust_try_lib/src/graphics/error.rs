@@ -0,0 +1,95 @@
+use std::{error::Error, fmt};
+
+use winit::window::WindowId;
+
+#[derive(Debug)]
+pub enum GraphicsError {
+    WindowCreation(winit::error::OsError),
+    ///No adapter for the backends, or for the surface of the window.
+    NoAdapter,
+    DeviceRequest(wgpu::RequestDeviceError),
+    ///The adapter can't present to the window.
+    SurfaceUnsupported,
+    WindowNotFound(WindowId),
+    ///Nothing to draw on at this frame, e.g. the window is minimized or the surface was lost.
+    NoSurfaceTexture(WindowId),
+    ///Out of memory while acquiring the surface texture of the window.
+    OutOfMemory(WindowId),
+    CursorGrab(winit::error::ExternalError),
+    ///Resources are not made yet.
+    NotReady,
+    ///Matrix which should be inverted is singular.
+    SingularMatrix,
+    BufferMap(wgpu::BufferAsyncError),
+    ///Buffer copies must be non zero multiples of wgpu::COPY_BUFFER_ALIGNMENT.
+    InvalidCopySize(wgpu::BufferAddress),
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphicsError::WindowCreation(error) => write!(f, "failed to create window: {}", error),
+            GraphicsError::NoAdapter => write!(f, "no compatible adapter"),
+            GraphicsError::DeviceRequest(error) => write!(f, "failed to request device: {}", error),
+            GraphicsError::SurfaceUnsupported => {
+                write!(f, "surface is incompatible with the adapter")
+            }
+            GraphicsError::WindowNotFound(window_id) => {
+                write!(f, "window {:?} is not found", window_id)
+            }
+            GraphicsError::NoSurfaceTexture(window_id) => {
+                write!(
+                    f,
+                    "no surface texture for window {:?} at this frame",
+                    window_id
+                )
+            }
+            GraphicsError::OutOfMemory(window_id) => {
+                write!(f, "out of memory for the surface of window {:?}", window_id)
+            }
+            GraphicsError::CursorGrab(error) => write!(f, "failed to grab cursor: {}", error),
+            GraphicsError::NotReady => write!(f, "resources are not ready"),
+            GraphicsError::SingularMatrix => write!(f, "matrix can't be inverted"),
+            GraphicsError::BufferMap(error) => write!(f, "failed to map buffer: {}", error),
+            GraphicsError::InvalidCopySize(size) => {
+                write!(f, "buffer of {} bytes can't be copied", size)
+            }
+        }
+    }
+}
+
+impl Error for GraphicsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GraphicsError::WindowCreation(error) => Some(error),
+            GraphicsError::DeviceRequest(error) => Some(error),
+            GraphicsError::CursorGrab(error) => Some(error),
+            GraphicsError::BufferMap(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for GraphicsError {
+    fn from(error: winit::error::OsError) -> Self {
+        GraphicsError::WindowCreation(error)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for GraphicsError {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        GraphicsError::DeviceRequest(error)
+    }
+}
+
+impl From<winit::error::ExternalError> for GraphicsError {
+    fn from(error: winit::error::ExternalError) -> Self {
+        GraphicsError::CursorGrab(error)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for GraphicsError {
+    fn from(error: wgpu::BufferAsyncError) -> Self {
+        GraphicsError::BufferMap(error)
+    }
+}
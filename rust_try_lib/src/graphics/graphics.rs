@@ -1,16 +1,40 @@
-use super::elements::*;
+use super::{elements::*, error::*};
+use crate::inputs::CursorMode;
 
 use std::{collections::hash_map::*, ops::Deref, sync::Arc};
 
 use winit::{
     event_loop::EventLoopWindowTarget,
-    window::{Window, WindowBuilder, WindowId},
+    window::{CursorIcon, Window, WindowBuilder, WindowId},
 };
 
 pub struct GraphicsConfig {
     pub title: &'static str,
 }
 
+///For windows added after start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+    ///Title of the application when empty.
+    pub title: String,
+    ///Platform decides when None.
+    pub size: Option<winit::dpi::PhysicalSize<u32>>,
+}
+
+impl WindowConfig {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            size: None,
+        }
+    }
+
+    pub fn with_size(mut self, size: winit::dpi::PhysicalSize<u32>) -> Self {
+        self.size = Some(size);
+        self
+    }
+}
+
 pub(super) struct GraphicsCore {
     pub queue: wgpu::Queue,
     pub device: wgpu::Device,
@@ -26,88 +50,104 @@ pub struct Graphics {
     pub(super) core: Arc<GraphicsCore>,
 
     config: GraphicsConfig,
+    ///Surfaces can't be used until resumed.
+    suspended: bool,
 }
 
-impl Graphics {
-    pub(crate) async fn new(
-        config: GraphicsConfig,
-        event_loop: &EventLoopWindowTarget<()>,
-    ) -> Self {
-        let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
-
-        let window = WindowBuilder::new()
-            .with_title(config.title)
-            .build(event_loop)
-            .unwrap();
-        let window_id = window.id();
-
-        let surface = unsafe { instance.create_surface(&window) };
+impl GraphicsCore {
+    ///Requested only when the adapter supports. Indirect drawing is merged into a multi draw with these.
+    const OPTIONAL_FEATURES: wgpu::Features =
+        wgpu::Features::MULTI_DRAW_INDIRECT.union(wgpu::Features::INDIRECT_FIRST_INSTANCE);
 
+    async fn new(
+        instance: wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface>,
+    ) -> Result<Self, GraphicsError> {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::HighPerformance,
                 force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                compatible_surface,
             })
             .await
-            .unwrap();
+            .ok_or(GraphicsError::NoAdapter)?;
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Initial Device"),
-                    features: wgpu::Features::empty(),
+                    features: adapter.features() & Self::OPTIONAL_FEATURES,
                     limits: wgpu::Limits::default(),
                 },
                 None,
             )
-            .await
-            .unwrap();
-
-        let window_size = window.inner_size();
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface
-                .get_preferred_format(&adapter)
-                .expect("Surface is incompatible with the adapter"),
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-        };
-        surface.configure(&device, &surface_config);
+            .await?;
+
+        Ok(Self {
+            queue,
+            device,
+            adapter,
+            instance,
+        })
+    }
+}
 
-        let depth_texture =
-            Texture::create_depth_texture(&device, &surface_config, "Depth Texture");
+impl Graphics {
+    pub(crate) async fn new<T: 'static>(
+        config: GraphicsConfig,
+        event_loop: &EventLoopWindowTarget<T>,
+    ) -> Result<Self, GraphicsError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
 
-        let mut window_surfaces = HashMap::new();
-        window_surfaces.insert(
-            window.id(),
-            WindowSurface {
-                window,
+        let window = WindowBuilder::new()
+            .with_title(config.title)
+            .build(event_loop)?;
+        let window_id = window.id();
 
-                surface,
-                surface_config,
+        let surface = unsafe { instance.create_surface(&window) };
 
-                surface_texture: None,
-                surface_texture_view: None,
+        let core = GraphicsCore::new(instance, Some(&surface)).await?;
+        let window_surface = WindowSurface::new(&core, window, surface)?;
 
-                depth_texture,
-            },
-        );
+        let mut window_surfaces = HashMap::new();
+        window_surfaces.insert(window_id, window_surface);
 
-        Self {
+        Ok(Self {
             config,
 
-            core: Arc::new(GraphicsCore {
-                instance,
-                adapter,
-                device,
-                queue,
-            }),
+            core: Arc::new(core),
 
             primary_window_id: Some(window_id),
             window_surfaces,
-        }
+            suspended: false,
+        })
+    }
+
+    ///Graphics without any window. For compute works and tests.
+    pub async fn new_headless(config: GraphicsConfig) -> Result<Self, GraphicsError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let core = GraphicsCore::new(instance, None).await?;
+
+        Ok(Self {
+            config,
+
+            core: Arc::new(core),
+
+            primary_window_id: None,
+            window_surfaces: HashMap::new(),
+            suspended: false,
+        })
+    }
+
+    ///Tests using it are ignored by default, and run with --ignored where there is an adapter.
+    #[cfg(test)]
+    pub(crate) fn headless_for_test(title: &'static str) -> Self {
+        pollster::block_on(Self::new_headless(GraphicsConfig { title }))
+            .expect("GPU tests need an adapter")
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.window_surfaces.is_empty()
     }
 
     pub fn primary_window_id(&self) -> Option<WindowId> {
@@ -128,60 +168,87 @@ impl Graphics {
         self.window_surfaces.get(&window_id)
     }
 
-    pub fn aspect(&self, window_id: WindowId) -> f32 {
-        let size = self
-            .window_surfaces
-            .get(&window_id)
-            .unwrap()
-            .inner_size()
-            .cast::<f32>();
-
-        size.width / size.height
+    pub(super) fn window_surface_or_err(
+        &self,
+        window_id: WindowId,
+    ) -> Result<&WindowSurface, GraphicsError> {
+        self.window_surface(window_id)
+            .ok_or(GraphicsError::WindowNotFound(window_id))
     }
-}
 
-impl Graphics {
-    pub fn add_window(&mut self, event_loop: &EventLoopWindowTarget<()>) -> Result<WindowId, ()> {
-        let window = WindowBuilder::new()
-            .with_title(self.config.title)
-            .build(event_loop)
-            .unwrap();
+    ///View of the surface texture acquired at this frame.
+    pub(super) fn surface_texture_view(
+        &self,
+        window_id: WindowId,
+    ) -> Result<(&WindowSurface, &wgpu::TextureView), GraphicsError> {
+        let window_surface = self.window_surface_or_err(window_id)?;
+        match window_surface.surface_texture_view {
+            Some(ref surface_texture_view) => Ok((window_surface, surface_texture_view)),
+            None => Err(GraphicsError::NoSurfaceTexture(window_id)),
+        }
+    }
 
-        let surface = unsafe { self.core.instance.create_surface(&window) };
-        let is_surface_supported = self.core.adapter.is_surface_supported(&surface);
-        if !is_surface_supported {
-            return Err(());
+    ///None when the window is not found or minimized.
+    pub fn aspect(&self, window_id: WindowId) -> Option<f32> {
+        let size = self.window_size(window_id)?;
+        if size.height == 0 {
+            return None;
         }
-        let window_size = window.inner_size();
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface
-                .get_preferred_format(&self.core.adapter)
-                .expect("Surface is incompatible with the adapter"),
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-        };
-        surface.configure(&self.core.device, &surface_config);
+        let size = size.cast::<f32>();
 
-        let depth_texture =
-            Texture::create_depth_texture(&self.core.device, &surface_config, "Depth Texture");
+        Some(size.width / size.height)
+    }
 
-        let window_id = window.id();
-        self.window_surfaces.insert(
-            window_id,
-            WindowSurface {
-                window,
+    pub fn window_size(&self, window_id: WindowId) -> Option<winit::dpi::PhysicalSize<u32>> {
+        self.window_surfaces
+            .get(&window_id)
+            .map(|window_surface| window_surface.inner_size())
+    }
 
-                surface,
-                surface_config,
+    ///Err when the window is not found or the platform can't grab. The window is left as it was then.
+    pub fn set_cursor_mode(
+        &self,
+        window_id: WindowId,
+        mode: CursorMode,
+    ) -> Result<(), GraphicsError> {
+        let window_surface = self.window_surface_or_err(window_id)?;
+        window_surface.set_cursor_grab(mode.is_grabbed())?;
+        window_surface.set_cursor_visible(mode.is_visible());
+        Ok(())
+    }
 
-                surface_texture: None,
-                surface_texture_view: None,
+    pub fn set_cursor_icon(
+        &self,
+        window_id: WindowId,
+        icon: CursorIcon,
+    ) -> Result<(), GraphicsError> {
+        let window_surface = self.window_surface_or_err(window_id)?;
+        window_surface.set_cursor_icon(icon);
+        Ok(())
+    }
+}
 
-                depth_texture,
-            },
-        );
+impl Graphics {
+    pub fn add_window<T: 'static>(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<T>,
+        config: &WindowConfig,
+    ) -> Result<WindowId, GraphicsError> {
+        let title = if config.title.is_empty() {
+            self.config.title
+        } else {
+            &config.title
+        };
+        let mut builder = WindowBuilder::new().with_title(title);
+        if let Some(size) = config.size {
+            builder = builder.with_inner_size(size);
+        }
+        let window = builder.build(event_loop)?;
+
+        let surface = unsafe { self.core.instance.create_surface(&window) };
+        let window_id = window.id();
+        let window_surface = WindowSurface::new(&self.core, window, surface)?;
+        self.window_surfaces.insert(window_id, window_surface);
         Ok(window_id)
     }
 
@@ -203,55 +270,84 @@ impl Graphics {
         if new_size.width > 0 && new_size.height > 0 && window_size == new_size {
             window_surface.surface_config.width = new_size.width;
             window_surface.surface_config.height = new_size.height;
+            window_surface.reconfigure(&self.core.device);
+        }
+    }
 
-            window_surface.surface_texture = None;
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    ///Surface textures are dropped, since the platform may destroy surfaces.
+    pub(crate) fn suspend(&mut self) {
+        for window_surface in self.window_surfaces.values_mut() {
             window_surface.surface_texture_view = None;
+            window_surface.surface_texture = None;
+        }
+        self.suspended = true;
+    }
 
-            window_surface
-                .surface
-                .configure(&self.core.device, &window_surface.surface_config);
+    ///Surfaces are recreated for the windows.
+    pub(crate) fn resume(&mut self) {
+        if !self.suspended {
+            return;
+        }
+        for window_surface in self.window_surfaces.values_mut() {
+            window_surface.surface =
+                unsafe { self.core.instance.create_surface(&window_surface.window) };
 
-            window_surface.depth_texture = Texture::create_depth_texture(
-                &self.core.device,
-                &window_surface.surface_config,
-                "depth_texture",
-            );
+            let window_size = window_surface.inner_size();
+            if window_size.width > 0 && window_size.height > 0 {
+                window_surface.surface_config.width = window_size.width;
+                window_surface.surface_config.height = window_size.height;
+            }
+            window_surface.reconfigure(&self.core.device);
         }
+        self.suspended = false;
     }
 
-    pub fn update(&mut self) {
-        for window_surface in self.window_surfaces.values_mut() {
+    ///Acquires surface textures. Windows without one can't be drawn at this frame.
+    ///Err when out of memory, which retrying at next frames won't fix. Other windows are still updated.
+    pub fn update(&mut self) -> Result<(), GraphicsError> {
+        if self.suspended {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for (window_id, window_surface) in self.window_surfaces.iter_mut() {
             window_surface.request_redraw();
 
             if let Some(_) = window_surface.surface_texture {
                 continue;
             }
+            //Minimized.
+            let window_size = window_surface.inner_size();
+            if window_size.width == 0 || window_size.height == 0 {
+                continue;
+            }
 
-            window_surface.surface_texture = match window_surface.surface.get_current_texture() {
-                Ok(surface_texture) => {
-                    window_surface.surface_texture_view = Some(
-                        surface_texture
-                            .texture
-                            .create_view(&wgpu::TextureViewDescriptor::default()),
-                    );
-
-                    Some(surface_texture)
+            let surface_texture = match window_surface.surface.get_current_texture() {
+                Ok(surface_texture) => Some(surface_texture),
+                Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+                    window_surface.surface_config.width = window_size.width;
+                    window_surface.surface_config.height = window_size.height;
+                    window_surface.reconfigure(&self.core.device);
+                    window_surface.surface.get_current_texture().ok()
                 }
-                Err(wgpu::SurfaceError::Outdated) => {
-                    window_surface
-                        .surface
-                        .configure(&self.core.device, &window_surface.surface_config);
-
-                    Some(
-                        window_surface
-                            .surface
-                            .get_current_texture()
-                            .expect("Error reconfiguring surface"),
-                    )
+                //Tried again at the next frame.
+                Err(wgpu::SurfaceError::Timeout) => None,
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    result = Err(GraphicsError::OutOfMemory(*window_id));
+                    None
                 }
-                e => panic!("Failed to acquire next swap chain texture!\n{:?}", e),
             };
+            window_surface.surface_texture_view = surface_texture.as_ref().map(|surface_texture| {
+                surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            });
+            window_surface.surface_texture = surface_texture;
         }
+        result
     }
 
     pub fn present(&mut self) {
@@ -276,6 +372,55 @@ pub(super) struct WindowSurface {
     pub window: Window,
 }
 
+impl WindowSurface {
+    fn new(
+        core: &GraphicsCore,
+        window: Window,
+        surface: wgpu::Surface,
+    ) -> Result<Self, GraphicsError> {
+        if !core.adapter.is_surface_supported(&surface) {
+            return Err(GraphicsError::SurfaceUnsupported);
+        }
+        let window_size = window.inner_size();
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface
+                .get_preferred_format(&core.adapter)
+                .ok_or(GraphicsError::SurfaceUnsupported)?,
+            //Surfaces can't be zero sized. Configured again when resized.
+            width: window_size.width.max(1),
+            height: window_size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        surface.configure(&core.device, &surface_config);
+
+        let depth_texture =
+            Texture::create_depth_texture(&core.device, &surface_config, "Depth Texture");
+
+        Ok(Self {
+            window,
+
+            surface,
+            surface_config,
+
+            surface_texture: None,
+            surface_texture_view: None,
+
+            depth_texture,
+        })
+    }
+
+    ///With surface_config, recreating the depth texture.
+    fn reconfigure(&mut self, device: &wgpu::Device) {
+        self.surface_texture = None;
+        self.surface_texture_view = None;
+
+        self.surface.configure(device, &self.surface_config);
+        self.depth_texture =
+            Texture::create_depth_texture(device, &self.surface_config, "depth_texture");
+    }
+}
+
 impl Deref for WindowSurface {
     type Target = Window;
 
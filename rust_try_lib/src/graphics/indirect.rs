@@ -0,0 +1,509 @@
+use super::{elements::*, graphics::*};
+
+use std::{collections::HashMap, mem};
+
+use wgpu::util::DeviceExt;
+
+use cgmath::*;
+
+///Where a mesh is placed inside of the shared vertex and index buffers.
+struct ArenaEntry {
+    slot_bounds: [f32; 4],
+    index_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+}
+
+///Cpu side contents of the buffers for a frame.
+struct Batched {
+    cull_instances: Vec<CullInstance>,
+    slots: Vec<MeshSlot>,
+    draws: Vec<DrawIndexedIndirect>,
+    draw_bases: Vec<u32>,
+}
+
+///Lays out instances by mesh. Meshes not in arena or without instances are skipped,
+///so slot is the index among the ones actually pushed.
+fn batch(
+    arena: &HashMap<u32, ArenaEntry>,
+    to_draw: &[u32],
+    instances: &HashMap<u32, Vec<Instance>>,
+    first_instance_feature: bool,
+) -> Batched {
+    let mut batched = Batched {
+        cull_instances: Vec::new(),
+        slots: Vec::with_capacity(to_draw.len()),
+        draws: Vec::with_capacity(to_draw.len()),
+        draw_bases: Vec::with_capacity(to_draw.len()),
+    };
+
+    for mesh_id in to_draw {
+        let (entry, instances) = match (arena.get(mesh_id), instances.get(mesh_id)) {
+            (Some(entry), Some(instances)) => (entry, instances),
+            _ => continue,
+        };
+        let slot = batched.slots.len() as u32;
+        let base = batched.cull_instances.len() as u32;
+
+        batched.cull_instances.extend(
+            instances
+                .iter()
+                .map(|instance| CullInstance::new(instance.transform_matrix(), slot)),
+        );
+        batched.slots.push(MeshSlot::new(entry.slot_bounds, base));
+        batched.draws.push(DrawIndexedIndirect {
+            index_count: entry.index_count,
+            instance_count: 0,
+            first_index: entry.first_index,
+            base_vertex: entry.base_vertex,
+            first_instance: if first_instance_feature { base } else { 0 },
+        });
+        batched.draw_bases.push(base);
+    }
+    batched
+}
+
+///Gpu driven path of Batch.
+///All meshes share single vertex and index buffer, so draws can be merged into a multi draw.
+///Instances are culled by compute shader and compacted into the visible instance buffer.
+pub struct IndirectBatch {
+    arena: HashMap<u32, ArenaEntry>,
+    vertices: Vec<ColorVertex>,
+    indices: Vec<u32>,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+
+    instance_capacity: u64,
+    slot_capacity: u64,
+    cull_instance_buffer: wgpu::Buffer,
+    visible_instance_buffer: wgpu::Buffer,
+    slot_buffer: wgpu::Buffer,
+    draw_buffer: wgpu::Buffer,
+    frustum_buffer: wgpu::Buffer,
+
+    cull_bind_group_layout: wgpu::BindGroupLayout,
+    cull_bind_group: wgpu::BindGroup,
+    cull_pipeline: wgpu::ComputePipeline,
+
+    ///Offsets of each slot in the visible instance buffer. Used when multi draw is not available.
+    draw_bases: Vec<u32>,
+    instance_count: u32,
+}
+
+impl IndirectBatch {
+    const INITIAL_INSTANCE_CAPACITY: u64 = 1024;
+    const INITIAL_SLOT_CAPACITY: u64 = 64;
+    const WORKGROUP_SIZE: u32 = 64;
+
+    pub(super) fn new(graphics_core: &GraphicsCore) -> Self {
+        let device = &graphics_core.device;
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let cull_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cull Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    storage_entry(1, true),
+                    storage_entry(2, true),
+                    storage_entry(3, false),
+                    storage_entry(4, false),
+                ],
+            });
+
+        let cull_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[&cull_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(&include_wgsl!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/shaders/cull_instances.wgsl"
+        )));
+
+        let cull_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: Some(&cull_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let vertex_buffer = Self::create_arena_buffer(
+            device,
+            "Arena Vertex Buffer",
+            &[0u8; 4],
+            wgpu::BufferUsages::VERTEX,
+        );
+        let index_buffer = Self::create_arena_buffer(
+            device,
+            "Arena Index Buffer",
+            &[0u8; 4],
+            wgpu::BufferUsages::INDEX,
+        );
+
+        let frustum_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frustum Buffer"),
+            size: mem::size_of::<Frustum>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let (cull_instance_buffer, visible_instance_buffer) =
+            Self::create_instance_buffers(device, Self::INITIAL_INSTANCE_CAPACITY);
+        let (slot_buffer, draw_buffer) =
+            Self::create_slot_buffers(device, Self::INITIAL_SLOT_CAPACITY);
+
+        let cull_bind_group = Self::create_cull_bind_group(
+            device,
+            &cull_bind_group_layout,
+            &frustum_buffer,
+            &cull_instance_buffer,
+            &slot_buffer,
+            &draw_buffer,
+            &visible_instance_buffer,
+        );
+
+        Self {
+            arena: HashMap::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            vertex_buffer,
+            index_buffer,
+
+            instance_capacity: Self::INITIAL_INSTANCE_CAPACITY,
+            slot_capacity: Self::INITIAL_SLOT_CAPACITY,
+            cull_instance_buffer,
+            visible_instance_buffer,
+            slot_buffer,
+            draw_buffer,
+            frustum_buffer,
+
+            cull_bind_group_layout,
+            cull_bind_group,
+            cull_pipeline,
+
+            draw_bases: Vec::new(),
+            instance_count: 0,
+        }
+    }
+
+    fn create_arena_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        contents: &[u8],
+        usage: wgpu::BufferUsages,
+    ) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage,
+        })
+    }
+
+    fn create_instance_buffers(
+        device: &wgpu::Device,
+        capacity: u64,
+    ) -> (wgpu::Buffer, wgpu::Buffer) {
+        let cull_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cull Instance Buffer"),
+            size: capacity * mem::size_of::<CullInstance>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let visible_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible Instance Buffer"),
+            size: capacity * mem::size_of::<Instance>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+        (cull_instance_buffer, visible_instance_buffer)
+    }
+
+    fn create_slot_buffers(device: &wgpu::Device, capacity: u64) -> (wgpu::Buffer, wgpu::Buffer) {
+        let slot_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Mesh Slot Buffer"),
+            size: capacity * mem::size_of::<MeshSlot>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let draw_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Draw Indexed Indirect Buffer"),
+            size: capacity * DrawIndexedIndirect::SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        (slot_buffer, draw_buffer)
+    }
+
+    fn create_cull_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        frustum_buffer: &wgpu::Buffer,
+        cull_instance_buffer: &wgpu::Buffer,
+        slot_buffer: &wgpu::Buffer,
+        draw_buffer: &wgpu::Buffer,
+        visible_instance_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cull Bind Group"),
+            layout,
+            entries: &[
+                frustum_buffer,
+                cull_instance_buffer,
+                slot_buffer,
+                draw_buffer,
+                visible_instance_buffer,
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl IndirectBatch {
+    ///Places mesh into the arena if it has never been seen.
+    pub(super) fn ensure_mesh(&mut self, graphics_core: &GraphicsCore, mesh: &Mesh) {
+        if self.arena.contains_key(&mesh.id()) {
+            return;
+        }
+
+        self.arena.insert(
+            mesh.id(),
+            ArenaEntry {
+                slot_bounds: mesh.bounds(),
+                index_count: mesh.indices().len() as u32,
+                first_index: self.indices.len() as u32,
+                base_vertex: self.vertices.len() as i32,
+            },
+        );
+        self.vertices.extend_from_slice(mesh.vertices());
+        self.indices.extend_from_slice(mesh.indices());
+
+        //New mesh is rare, so whole arena is just uploaded again.
+        let device = &graphics_core.device;
+        self.vertex_buffer = Self::create_arena_buffer(
+            device,
+            "Arena Vertex Buffer",
+            bytemuck::cast_slice(&self.vertices),
+            wgpu::BufferUsages::VERTEX,
+        );
+        self.index_buffer = Self::create_arena_buffer(
+            device,
+            "Arena Index Buffer",
+            bytemuck::cast_slice(&self.indices),
+            wgpu::BufferUsages::INDEX,
+        );
+    }
+
+    fn ensure_capacity(&mut self, graphics_core: &GraphicsCore, instances: u64, slots: u64) {
+        let device = &graphics_core.device;
+        let mut recreated = false;
+
+        if instances > self.instance_capacity {
+            self.instance_capacity = instances.next_power_of_two();
+            let (cull_instance_buffer, visible_instance_buffer) =
+                Self::create_instance_buffers(device, self.instance_capacity);
+            self.cull_instance_buffer = cull_instance_buffer;
+            self.visible_instance_buffer = visible_instance_buffer;
+            recreated = true;
+        }
+
+        if slots > self.slot_capacity {
+            self.slot_capacity = slots.next_power_of_two();
+            let (slot_buffer, draw_buffer) = Self::create_slot_buffers(device, self.slot_capacity);
+            self.slot_buffer = slot_buffer;
+            self.draw_buffer = draw_buffer;
+            recreated = true;
+        }
+
+        if recreated {
+            self.cull_bind_group = Self::create_cull_bind_group(
+                device,
+                &self.cull_bind_group_layout,
+                &self.frustum_buffer,
+                &self.cull_instance_buffer,
+                &self.slot_buffer,
+                &self.draw_buffer,
+                &self.visible_instance_buffer,
+            );
+        }
+    }
+
+    ///Uploads batched instances and draw arguments, then records culling into encoder.
+    ///Returns count of draws.
+    pub(super) fn prepare(
+        &mut self,
+        graphics_core: &GraphicsCore,
+        encoder: &mut wgpu::CommandEncoder,
+        cull_matrix: Option<Matrix4<f32>>,
+        to_draw: &[u32],
+        instances: &HashMap<u32, Vec<Instance>>,
+    ) -> u32 {
+        let instance_count = to_draw
+            .iter()
+            .filter_map(|mesh_id| instances.get(mesh_id))
+            .map(|instances| instances.len() as u64)
+            .sum::<u64>();
+        self.ensure_capacity(graphics_core, instance_count, to_draw.len() as u64);
+
+        //first_instance can't be used without the feature, so offsets are bound per draw instead.
+        let first_instance_feature = graphics_core
+            .device
+            .features()
+            .contains(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+
+        let Batched {
+            cull_instances,
+            slots,
+            draws,
+            draw_bases,
+        } = batch(&self.arena, to_draw, instances, first_instance_feature);
+        self.draw_bases = draw_bases;
+        self.instance_count = cull_instances.len() as u32;
+
+        if draws.is_empty() {
+            return 0;
+        }
+
+        let queue = &graphics_core.queue;
+        queue.write_buffer(
+            &self.frustum_buffer,
+            0,
+            bytemuck::bytes_of(&match cull_matrix {
+                Some(view_proj) => Frustum::new(view_proj, self.instance_count),
+                None => Frustum::everything(self.instance_count),
+            }),
+        );
+        queue.write_buffer(
+            &self.cull_instance_buffer,
+            0,
+            bytemuck::cast_slice(&cull_instances),
+        );
+        queue.write_buffer(&self.slot_buffer, 0, bytemuck::cast_slice(&slots));
+        queue.write_buffer(&self.draw_buffer, 0, bytemuck::cast_slice(&draws));
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Cull Pass"),
+            });
+            compute_pass.set_pipeline(&self.cull_pipeline);
+            compute_pass.set_bind_group(0, &self.cull_bind_group, &[]);
+            compute_pass.dispatch(self.instance_count.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+        }
+
+        draws.len() as u32
+    }
+
+    ///Records draws prepared by prepare. Uses multi draw when adapter allows.
+    pub(super) fn draw<'a>(
+        &'a self,
+        graphics_core: &GraphicsCore,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        draw_count: u32,
+    ) {
+        if draw_count == 0 {
+            return;
+        }
+
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        let features = graphics_core.device.features();
+        if features
+            .contains(wgpu::Features::MULTI_DRAW_INDIRECT | wgpu::Features::INDIRECT_FIRST_INSTANCE)
+        {
+            render_pass.set_vertex_buffer(1, self.visible_instance_buffer.slice(..));
+            render_pass.multi_draw_indexed_indirect(&self.draw_buffer, 0, draw_count);
+        } else {
+            let first_instance_feature = features.contains(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+            for (i, base) in self.draw_bases.iter().enumerate() {
+                let offset = if first_instance_feature {
+                    0
+                } else {
+                    *base as wgpu::BufferAddress * mem::size_of::<Instance>() as wgpu::BufferAddress
+                };
+                render_pass.set_vertex_buffer(1, self.visible_instance_buffer.slice(offset..));
+                render_pass.draw_indexed_indirect(
+                    &self.draw_buffer,
+                    i as wgpu::BufferAddress * DrawIndexedIndirect::SIZE,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(first_index: u32) -> ArenaEntry {
+        ArenaEntry {
+            slot_bounds: [0.0, 0.0, 0.0, 1.0],
+            index_count: 3,
+            first_index,
+            base_vertex: 0,
+        }
+    }
+
+    #[test]
+    fn skipped_meshes_keep_slots_packed() {
+        let arena = vec![(1, entry(0)), (2, entry(3)), (3, entry(6))]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let instance = Instance::from_transform_matrix(Matrix4::identity());
+        //1 has no instances and 4 is not in arena.
+        let instances = vec![
+            (2, vec![instance; 2]),
+            (3, vec![instance]),
+            (4, vec![instance]),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let batched = batch(&arena, &[1, 2, 4, 3], &instances, true);
+        assert_eq!(batched.slots.len(), 2);
+        assert_eq!(batched.draws.len(), 2);
+        assert_eq!(
+            batched
+                .cull_instances
+                .iter()
+                .map(|instance| instance.slot)
+                .collect::<Vec<_>>(),
+            vec![0, 0, 1]
+        );
+        assert_eq!(batched.draw_bases, vec![0, 2]);
+        assert_eq!(
+            (
+                batched.draws[1].first_index,
+                batched.draws[1].first_instance
+            ),
+            (6, 2)
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//!Gpu particle effects. Simulated by compute shader, drawn as camera facing billboards.
+use super::{compute::*, elements::*, error::*, graphics::*};
+
+use std::sync::Arc;
+
+use winit::window::WindowId;
+
+use cgmath::*;
+
+///Piecewise linear values over normalized life(0..1).
+#[derive(Clone, Debug)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: CurveValue> Curve<T> {
+    ///keys don't need to be sorted.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        Self { keys }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> T {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return T::default(),
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+
+        for pair in self.keys.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if t <= to.0 {
+                let range = to.0 - from.0;
+                let s = if range > f32::EPSILON {
+                    (t - from.0) / range
+                } else {
+                    1.0
+                };
+                return T::lerp(from.1, to.1, s);
+            }
+        }
+        last.1
+    }
+
+    ///Samples evenly into fixed count of keys for gpu.
+    fn bake(&self) -> [T; ParticleEmitter::CURVE_KEYS] {
+        let mut baked = [T::default(); ParticleEmitter::CURVE_KEYS];
+        for (i, value) in baked.iter_mut().enumerate() {
+            *value = self.sample(i as f32 / (ParticleEmitter::CURVE_KEYS - 1) as f32);
+        }
+        baked
+    }
+}
+
+pub trait CurveValue: Copy + Default {
+    fn lerp(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl CurveValue for f32 {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        from + (to - from) * t
+    }
+}
+
+impl CurveValue for [f32; 4] {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        let mut ret = from;
+        for (i, value) in ret.iter_mut().enumerate() {
+            *value = f32::lerp(from[i], to[i], t);
+        }
+        ret
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ParticleEmitterConfig {
+    pub max_particles: u32,
+    ///Particles per second
+    pub spawn_rate: f32,
+    ///Seconds
+    pub lifetime: f32,
+    ///0..1. Lifetime is shortened randomly up to this ratio.
+    pub lifetime_variance: f32,
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    ///Random offset of velocity per axis.
+    pub velocity_spread: f32,
+    pub gravity: Vector3<f32>,
+    pub color_over_life: Curve<[f32; 4]>,
+    pub size_over_life: Curve<f32>,
+    ///Same seed and same updates give same particles.
+    pub seed: u32,
+}
+
+impl Default for ParticleEmitterConfig {
+    fn default() -> Self {
+        Self {
+            max_particles: 1024,
+            spawn_rate: 64.0,
+            lifetime: 2.0,
+            lifetime_variance: 0.0,
+            position: point3(0.0, 0.0, 0.0),
+            velocity: vec3(0.0, 1.0, 0.0),
+            velocity_spread: 0.5,
+            gravity: vec3(0.0, -9.8, 0.0),
+            color_over_life: Curve::new(vec![
+                (0.0, [1.0, 1.0, 1.0, 1.0]),
+                (1.0, [1.0, 1.0, 1.0, 0.0]),
+            ]),
+            size_over_life: Curve::constant(0.1),
+            seed: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Particle {
+    ///xyz for position, w for age
+    pub position: [f32; 4],
+    ///xyz for velocity, w for lifetime
+    pub velocity: [f32; 4],
+}
+
+impl Particle {
+    pub fn is_alive(&self) -> bool {
+        self.position[3] < self.velocity[3]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimulationUniform {
+    emitter: [f32; 4],
+    velocity: [f32; 4],
+    gravity: [f32; 4],
+    spawn: [u32; 4],
+    seed: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct RenderUniform {
+    view_proj: [[f32; 4]; 4],
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+    colors: [[f32; 4]; ParticleEmitter::CURVE_KEYS],
+    sizes: [[f32; 4]; ParticleEmitter::CURVE_KEYS],
+}
+
+///Decides which ring buffer range is spawned in each update. Kept apart from gpu for determinism.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParticleSpawner {
+    accumulator: f32,
+    cursor: u32,
+    serial: u32,
+}
+
+impl ParticleSpawner {
+    ///Returns (start, count, serial of first spawning particle).
+    pub fn spawn(&mut self, spawn_rate: f32, delta: f32, max_particles: u32) -> (u32, u32, u32) {
+        self.accumulator += spawn_rate.max(0.0) * delta.max(0.0);
+        let count = (self.accumulator.floor() as u32).min(max_particles);
+        self.accumulator -= self.accumulator.floor();
+
+        let ret = (self.cursor, count, self.serial);
+        if max_particles > 0 {
+            self.cursor = (self.cursor + count) % max_particles;
+        }
+        self.serial = self.serial.wrapping_add(count);
+        ret
+    }
+}
+
+pub struct ParticleEmitter {
+    config: ParticleEmitterConfig,
+    spawner: ParticleSpawner,
+
+    particle_buffer: ComputeBuffer,
+    simulation_buffer: ComputeBuffer,
+    simulation_pipeline: ComputePipeline,
+    simulation_bind_group: ComputeBindGroup,
+
+    render_pipeline: Option<wgpu::RenderPipeline>,
+    render_buffer: wgpu::Buffer,
+    render_bind_group: Option<wgpu::BindGroup>,
+
+    graphics_core: Arc<GraphicsCore>,
+}
+
+impl ParticleEmitter {
+    pub const CURVE_KEYS: usize = 8;
+    const WORKGROUP_SIZE: u32 = 64;
+
+    ///target_window_id decides render target format. None for simulation only(e.g. headless).
+    pub fn new(
+        graphics: &Graphics,
+        target_window_id: Option<WindowId>,
+        config: ParticleEmitterConfig,
+    ) -> Self {
+        let device = &graphics.core.device;
+
+        let particle_buffer = graphics.create_compute_buffer_zeroed(
+            "Particle",
+            ComputeBufferUsage::Storage,
+            config.max_particles.max(1) as wgpu::BufferAddress
+                * std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+        );
+        let simulation_buffer = graphics.create_compute_buffer_zeroed(
+            "Particle Simulation",
+            ComputeBufferUsage::Uniform,
+            std::mem::size_of::<SimulationUniform>() as wgpu::BufferAddress,
+        );
+        let simulation_pipeline = graphics.create_compute_pipeline(
+            "Particle Simulation",
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../assets/shaders/particle_simulate.wgsl"
+            )),
+            "cs_main",
+        );
+        let simulation_bind_group = simulation_pipeline.bind(
+            0,
+            &[
+                (0, ComputeResource::Buffer(&simulation_buffer)),
+                (1, ComputeResource::Buffer(&particle_buffer)),
+            ],
+        );
+
+        let render_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Render Buffer"),
+            size: std::mem::size_of::<RenderUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let render_pipeline = target_window_id
+            .and_then(|window_id| graphics.window_surface(window_id))
+            .map(|window_surface| {
+                Self::create_render_pipeline(device, window_surface.surface_config.format)
+            });
+        let render_bind_group = render_pipeline.as_ref().map(|render_pipeline| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Particle Render Bind Group"),
+                layout: &render_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: render_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particle_buffer.raw().as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        Self {
+            config,
+            spawner: ParticleSpawner::default(),
+
+            particle_buffer,
+            simulation_buffer,
+            simulation_pipeline,
+            simulation_bind_group,
+
+            render_pipeline,
+            render_buffer,
+            render_bind_group,
+
+            graphics_core: graphics.core.clone(),
+        }
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(&include_wgsl!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/shaders/particle_render.wgsl"
+        )));
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Render Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            //Tested against scene, but particles don't occlude each other.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    pub fn config(&self) -> &ParticleEmitterConfig {
+        &self.config
+    }
+
+    pub fn set_position(&mut self, position: Point3<f32>) {
+        self.config.position = position;
+    }
+
+    pub fn set_spawn_rate(&mut self, spawn_rate: f32) {
+        self.config.spawn_rate = spawn_rate;
+    }
+
+    ///Reads particles back from gpu. Blocks until simulation is done.
+    pub fn read_particles(&self) -> Result<Vec<Particle>, GraphicsError> {
+        self.particle_buffer.read_blocking()
+    }
+}
+
+impl ParticleEmitter {
+    ///Spawns and simulates particles for delta seconds.
+    pub fn update(&mut self, delta: f32) {
+        let config = &self.config;
+        let (spawn_start, spawn_count, spawn_serial) =
+            self.spawner
+                .spawn(config.spawn_rate, delta, config.max_particles);
+
+        let uniform = SimulationUniform {
+            emitter: config
+                .position
+                .to_vec()
+                .extend(config.velocity_spread)
+                .into(),
+            velocity: config.velocity.extend(config.lifetime).into(),
+            gravity: config.gravity.extend(delta).into(),
+            spawn: [spawn_start, spawn_count, config.max_particles, spawn_serial],
+            seed: [
+                config.seed,
+                config.lifetime_variance.clamp(0.0, 1.0).to_bits(),
+                0,
+                0,
+            ],
+        };
+        self.simulation_buffer
+            .write(0, std::slice::from_ref(&uniform));
+
+        self.simulation_pipeline.dispatch(
+            &[&self.simulation_bind_group],
+            [config.max_particles.div_ceil(Self::WORKGROUP_SIZE), 1, 1],
+        );
+    }
+
+    ///Draws over the target window after main pass. view for billboard axes.
+    pub fn render(
+        &mut self,
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>,
+    ) -> Result<(), GraphicsError> {
+        let (render_pipeline, render_bind_group) =
+            match (&self.render_pipeline, &self.render_bind_group) {
+                (Some(render_pipeline), Some(render_bind_group)) => {
+                    (render_pipeline, render_bind_group)
+                }
+                _ => return Err(GraphicsError::NotReady),
+            };
+        let (window_surface, surface_texture_view) =
+            graphics.surface_texture_view(target_window_id)?;
+
+        let uniform = RenderUniform {
+            view_proj: (proj * view).into(),
+            camera_right: [view.x.x, view.y.x, view.z.x, 0.0],
+            camera_up: [view.x.y, view.y.y, view.z.y, 0.0],
+            colors: self.config.color_over_life.bake(),
+            sizes: self
+                .config
+                .size_over_life
+                .bake()
+                .map(|size| [size, 0.0, 0.0, 0.0]),
+        };
+        self.graphics_core
+            .queue
+            .write_buffer(&self.render_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let mut encoder =
+            self.graphics_core
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Particle Encoder"),
+                });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Particle Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: surface_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &window_surface.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(0, render_bind_group, &[]);
+            render_pass.draw(0..6, 0..self.config.max_particles);
+        }
+
+        self.graphics_core
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn curve_samples() {
+        let curve = Curve::new(vec![(1.0, 2.0), (0.0, 0.0), (0.5, 4.0)]);
+        assert_eq!(curve.sample(-1.0), 0.0);
+        assert_eq!(curve.sample(0.25), 2.0);
+        assert_eq!(curve.sample(0.75), 3.0);
+        assert_eq!(curve.sample(2.0), 2.0);
+        assert_eq!(Curve::<f32>::new(vec![]).sample(0.5), 0.0);
+    }
+
+    #[test]
+    fn spawner_accumulates() {
+        let mut spawner = ParticleSpawner::default();
+        assert_eq!(spawner.spawn(10.0, 0.05, 4), (0, 0, 0));
+        assert_eq!(spawner.spawn(10.0, 0.25, 4), (0, 3, 0));
+        assert_eq!(spawner.spawn(10.0, 0.1, 4), (3, 1, 3));
+        assert_eq!(spawner.spawn(10.0, 1.0, 4), (0, 4, 4));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter, run with --ignored"]
+    fn same_seed_same_particles() {
+        let graphics = Graphics::headless_for_test("Particle Test");
+
+        let simulate = |seed| {
+            let mut emitter = ParticleEmitter::new(
+                &graphics,
+                None,
+                ParticleEmitterConfig {
+                    max_particles: 128,
+                    spawn_rate: 16.0,
+                    seed,
+                    ..Default::default()
+                },
+            );
+            for _ in 0..20 {
+                emitter.update(0.0625);
+            }
+            emitter.read_particles().unwrap()
+        };
+
+        let particles = simulate(7);
+        assert_eq!(particles.iter().filter(|p| p.is_alive()).count(), 20);
+        assert_eq!(particles, simulate(7));
+        assert_ne!(particles, simulate(8));
+    }
+}
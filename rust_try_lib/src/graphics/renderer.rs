@@ -1,4 +1,4 @@
-use super::{elements::*, graphics::*};
+use super::{elements::*, error::*, graphics::*, indirect::*};
 
 use std::{collections::HashMap, num::*, sync::Arc};
 
@@ -43,8 +43,19 @@ pub struct BindGroupConfig {
 ///window, surface 정보, render_pipeline 별 batch.
 pub struct Renderer {
     render_pipeline: wgpu::RenderPipeline,
+    //Kept to make the reflecting pipeline when an environment is set.
+    shader: wgpu::ShaderModule,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    format: wgpu::TextureFormat,
+
+    environment: Option<Environment>,
+    eye: Point3<f32>,
 
     batch: Batch,
+    indirect: Option<IndirectBatch>,
+    cull_matrix: Option<Matrix4<f32>>,
+
+    clear_color: wgpu::Color,
 
     bind_buffers: Vec<Vec<wgpu::Buffer>>,
     bind_groups: Vec<wgpu::BindGroup>,
@@ -98,57 +109,18 @@ impl Renderer {
                 "/../assets/shaders/view_projection.wgsl"
             )));
 
-        let render_pipeline =
-            graphics
-                .core
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("Render Pipeline"),
-                    layout: Some(&render_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: "vs_main",
-                        buffers: &[ColorVertex::buffer_layout(), Instance::buffer_layout()],
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: "fs_main",
-                        targets: &[wgpu::ColorTargetState {
-                            format: graphics
-                                .window_surface(target_window_id)
-                                .expect("Target window doesn't exist")
-                                .surface_config
-                                .format,
-                            blend: Some(wgpu::BlendState {
-                                alpha: wgpu::BlendComponent::REPLACE,
-                                color: wgpu::BlendComponent::REPLACE,
-                            }),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        }],
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        unclipped_depth: false,
-                        conservative: false,
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: Texture::DEPTH_FORMAT,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState::default(),
-                    }),
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                });
+        let format = graphics
+            .window_surface(target_window_id)
+            .expect("Target window doesn't exist")
+            .surface_config
+            .format;
+        let render_pipeline = create_render_pipeline(
+            &graphics.core.device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_main",
+            format,
+        );
 
         let bind_buffers = bind_group_configs
             .iter()
@@ -195,8 +167,23 @@ impl Renderer {
         Self {
             graphics_core: graphics.core.clone(),
             render_pipeline,
+            shader,
+            bind_group_layouts,
+            format,
+
+            environment: None,
+            eye: Point3::origin(),
 
             batch: Batch::new(&graphics.core),
+            indirect: None,
+            cull_matrix: None,
+
+            clear_color: wgpu::Color {
+                r: 0.05,
+                g: 0.05,
+                b: 0.05,
+                a: 1.0,
+            },
 
             bind_buffers,
             bind_groups,
@@ -204,25 +191,78 @@ impl Renderer {
     }
 }
 
+impl Renderer {
+    ///Switches to gpu driven path. Draw arguments are built by culling compute shader.
+    ///Meshes batched before switching are uploaded again on next batch.
+    pub fn set_indirect(&mut self, indirect: bool) {
+        if indirect == self.indirect.is_some() {
+            return;
+        }
+
+        self.indirect = if indirect {
+            Some(IndirectBatch::new(&self.graphics_core))
+        } else {
+            None
+        };
+        self.batch.clear();
+    }
+
+    pub fn is_indirect(&self) -> bool {
+        self.indirect.is_some()
+    }
+
+    ///Background where nothing is drawn. Skybox covers it when rendered after.
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
+    }
+
+    ///Lit meshes reflect the cube texture by reflectivity of 0 to 1. None stops reflecting.
+    ///Bound after the bind groups of the renderer, which the lit shader expects at group 1.
+    pub fn set_environment(&mut self, texture: Option<&Texture>, reflectivity: f32) {
+        self.environment = texture.map(|texture| {
+            Environment::new(
+                &self.graphics_core.device,
+                &self.bind_group_layouts,
+                &self.shader,
+                self.format,
+                texture,
+                reflectivity,
+            )
+        });
+    }
+
+    pub fn has_environment(&self) -> bool {
+        self.environment.is_some()
+    }
+
+    ///Where reflections are seen from, usually the camera position.
+    pub fn set_eye(&mut self, eye: Point3<f32>) {
+        self.eye = eye;
+    }
+
+    ///View projection matrix used for frustum culling of indirect path. None disables culling.
+    pub fn set_cull_matrix(&mut self, cull_matrix: Option<Matrix4<f32>>) {
+        self.cull_matrix = cull_matrix;
+    }
+}
+
 impl Renderer {
     pub fn render(
         &mut self,
         graphics: &Graphics,
         target_window_id: WindowId,
         bind_resources: &[&[Option<Matrix4<f32>>]],
-    ) -> Result<(), ()> {
-        let window_surface = match graphics.window_surface(target_window_id) {
-            Some(window_surface) => window_surface,
-            _ => return Err(()),
-        };
-
-        let surface_texture_view = match window_surface.surface_texture_view {
-            Some(ref surface_texture_view) => surface_texture_view,
-            _ => unsafe {
-                debug_assert!(false, "Attempted to use None value.");
-                std::hint::unreachable_unchecked()
-            },
-        };
+    ) -> Result<(), GraphicsError> {
+        let (window_surface, surface_texture_view) =
+            match graphics.surface_texture_view(target_window_id) {
+                Ok(surface) => surface,
+                Err(error) => {
+                    //Batched ones are dropped as if drawn, not to pile up.
+                    self.batch.to_draw.clear();
+                    self.batch.instances.clear();
+                    return Err(error);
+                }
+            };
 
         for (i, bind_resources) in bind_resources.iter().enumerate() {
             for (j, bind_resource) in bind_resources.iter().enumerate() {
@@ -236,6 +276,19 @@ impl Renderer {
             }
         }
 
+        if let Some(ref environment) = self.environment {
+            self.graphics_core.queue.write_buffer(
+                &environment.buffer,
+                0,
+                bytemuck::cast_slice(&[
+                    self.eye.x,
+                    self.eye.y,
+                    self.eye.z,
+                    environment.reflectivity,
+                ]),
+            );
+        }
+
         let mut encoder =
             self.graphics_core
                 .device
@@ -243,6 +296,22 @@ impl Renderer {
                     label: Some("Render Encoder"),
                 });
 
+        //Indirect path consumes to_draw here, so direct path below draws nothing.
+        let indirect_draw_count = match self.indirect {
+            Some(ref mut indirect) => {
+                let draw_count = indirect.prepare(
+                    &self.graphics_core,
+                    &mut encoder,
+                    self.cull_matrix,
+                    &self.batch.to_draw,
+                    &self.batch.instances,
+                );
+                self.batch.to_draw.clear();
+                draw_count
+            }
+            None => 0,
+        };
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -250,12 +319,7 @@ impl Renderer {
                     view: &surface_texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.05,
-                            g: 0.05,
-                            b: 0.05,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: true,
                     },
                 }],
@@ -269,28 +333,34 @@ impl Renderer {
                 }),
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            match self.environment {
+                Some(ref environment) => {
+                    render_pass.set_pipeline(&environment.render_pipeline);
+                    render_pass.set_bind_group(
+                        self.bind_groups.len() as u32,
+                        &environment.bind_group,
+                        &[],
+                    );
+                }
+                None => render_pass.set_pipeline(&self.render_pipeline),
+            }
             for (i, bind_group) in self.bind_groups.iter().enumerate() {
                 render_pass.set_bind_group(i as u32, bind_group, &[]);
             }
 
+            if let Some(ref indirect) = self.indirect {
+                indirect.draw(&self.graphics_core, &mut render_pass, indirect_draw_count);
+            }
+
             let mut instance_start = 0u64;
             for mesh_id in self.batch.to_draw.drain(..) {
-                let mesh_buffer = match self.batch.mesh_buffers.get(&mesh_id) {
-                    Some(mesh_buffer) => mesh_buffer,
-                    _ => unsafe {
-                        debug_assert!(false, "Attempted to use empty value.");
-                        std::hint::unreachable_unchecked()
-                    },
-                };
-
-                let instances = match self.batch.instances.remove(&mesh_id) {
-                    Some(instances) => instances,
-                    _ => unsafe {
-                        debug_assert!(false, "Attempted to use empty value.");
-                        std::hint::unreachable_unchecked()
-                    },
-                };
+                let instances = self.batch.instances.remove(&mesh_id);
+                let (mesh_buffer, instances) =
+                    match (self.batch.mesh_buffers.get(&mesh_id), instances) {
+                        (Some(mesh_buffer), Some(instances)) => (mesh_buffer, instances),
+                        //Batch always has both, but nothing is drawn rather than trusting it.
+                        _ => continue,
+                    };
 
                 self.graphics_core.queue.write_buffer(
                     &self.batch.instance_buffer,
@@ -338,8 +408,159 @@ impl Renderer {
         rotation: Quaternion<f32>,
         scale: Vector3<f32>,
     ) {
-        self.batch
-            .batch(&self.graphics_core, mesh, position, rotation, scale);
+        match self.indirect {
+            Some(ref mut indirect) => indirect.ensure_mesh(&self.graphics_core, mesh),
+            None => self.batch.ensure_mesh_buffer(&self.graphics_core, mesh),
+        }
+        self.batch.batch(mesh, position, rotation, scale);
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[ColorVertex::buffer_layout(), Instance::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState {
+                    alpha: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+///Cube map with the pipeline which reflects it.
+struct Environment {
+    render_pipeline: wgpu::RenderPipeline,
+    ///Eye position and reflectivity.
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    reflectivity: f32,
+}
+
+impl Environment {
+    fn new(
+        device: &wgpu::Device,
+        bind_group_layouts: &[wgpu::BindGroupLayout],
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        texture: &Texture,
+        reflectivity: f32,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Environment Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(16),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Render Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts
+                .iter()
+                .chain(std::iter::once(&bind_group_layout))
+                .collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+        });
+        let render_pipeline =
+            create_render_pipeline(device, &layout, shader, "fs_reflect", format);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Environment Buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Environment Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        });
+
+        Self {
+            render_pipeline,
+            buffer,
+            bind_group,
+            reflectivity: reflectivity.clamp(0.0, 1.0),
+        }
     }
 }
 
@@ -378,14 +599,7 @@ impl Batch {
 }
 
 impl Batch {
-    pub(super) fn batch(
-        &mut self,
-        graphics_core: &GraphicsCore,
-        mesh: &Mesh,
-        position: Point3<f32>,
-        rotation: Quaternion<f32>,
-        scale: Vector3<f32>,
-    ) {
+    pub(super) fn ensure_mesh_buffer(&mut self, graphics_core: &GraphicsCore, mesh: &Mesh) {
         let mesh_id = mesh.id();
 
         match self.last_mesh_id {
@@ -397,6 +611,16 @@ impl Batch {
                 }
             }
         }
+    }
+
+    pub(super) fn batch(
+        &mut self,
+        mesh: &Mesh,
+        position: Point3<f32>,
+        rotation: Quaternion<f32>,
+        scale: Vector3<f32>,
+    ) {
+        let mesh_id = mesh.id();
 
         let instance = Instance::new(position, rotation, scale);
         if let Some(value) = self.instances.get_mut(&mesh_id) {
@@ -408,4 +632,11 @@ impl Batch {
 
         self.last_mesh_id = Some(mesh_id);
     }
+
+    pub(super) fn clear(&mut self) {
+        self.mesh_buffers.clear();
+        self.to_draw.clear();
+        self.instances.clear();
+        self.last_mesh_id = None;
+    }
 }
@@ -0,0 +1,289 @@
+use super::{elements::*, error::*, graphics::*};
+
+use std::sync::Arc;
+
+use winit::window::WindowId;
+
+use cgmath::*;
+
+///Draws cubemap behind everything. Should be rendered after main pass.
+pub struct Skybox {
+    texture: Texture,
+
+    render_pipeline: wgpu::RenderPipeline,
+    sky_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    graphics_core: Arc<GraphicsCore>,
+}
+
+impl Skybox {
+    ///texture should be a cube texture.
+    pub fn new(graphics: &Graphics, target_window_id: WindowId, texture: Texture) -> Self {
+        let device = &graphics.core.device;
+
+        let shader = device.create_shader_module(&include_wgsl!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/shaders/skybox.wgsl"
+        )));
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Render Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: graphics
+                        .window_surface(target_window_id)
+                        .expect("Target window doesn't exist")
+                        .surface_config
+                        .format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            //Sky is at the far plane, so only empty pixels pass.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let sky_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sky Buffer"),
+            size: std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &render_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sky_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+
+            render_pipeline,
+            sky_buffer,
+            bind_group,
+
+            graphics_core: graphics.core.clone(),
+        }
+    }
+
+    ///Six encoded images ordered as +X, -X, +Y, -Y, +Z, -Z.
+    pub fn from_bytes(
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        faces: [&[u8]; 6],
+    ) -> Result<Self, image::error::ImageError> {
+        let texture = Texture::cube_from_bytes(
+            &graphics.core.device,
+            &graphics.core.queue,
+            faces,
+            "Skybox Texture",
+        )?;
+        Ok(Self::new(graphics, target_window_id, texture))
+    }
+
+    pub fn from_equirectangular_bytes(
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        bytes: &[u8],
+        face_size: u32,
+    ) -> Result<Self, image::error::ImageError> {
+        let texture = Texture::cube_from_equirectangular_bytes(
+            &graphics.core.device,
+            &graphics.core.queue,
+            bytes,
+            face_size,
+            "Skybox Texture",
+        )?;
+        Ok(Self::new(graphics, target_window_id, texture))
+    }
+
+    ///Radiance HDR(.hdr) image, so the sky keeps its brightness over 1 for reflections.
+    pub fn from_equirectangular_hdr_bytes(
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        bytes: &[u8],
+        face_size: u32,
+    ) -> Result<Self, image::error::ImageError> {
+        let texture = Texture::cube_from_equirectangular_hdr_bytes(
+            &graphics.core.device,
+            &graphics.core.queue,
+            bytes,
+            face_size,
+            "Skybox Texture",
+        )?;
+        Ok(Self::new(graphics, target_window_id, texture))
+    }
+
+    ///Cube texture. Also usable as environment map for reflections, see Renderer::set_environment.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    ///Removes translation, so sky never gets closer.
+    pub fn rotation_only(view: Matrix4<f32>) -> Matrix4<f32> {
+        let mut rotation = view;
+        rotation.w = vec4(0.0, 0.0, 0.0, 1.0);
+        rotation
+    }
+}
+
+impl Skybox {
+    pub fn render(
+        &mut self,
+        graphics: &Graphics,
+        target_window_id: WindowId,
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>,
+    ) -> Result<(), GraphicsError> {
+        let (window_surface, surface_texture_view) =
+            graphics.surface_texture_view(target_window_id)?;
+
+        let inv_view_proj = (proj * Self::rotation_only(view))
+            .invert()
+            .ok_or(GraphicsError::SingularMatrix)?;
+        self.graphics_core.queue.write_buffer(
+            &self.sky_buffer,
+            0,
+            bytemuck::cast_slice(AsRef::<[[f32; 4]; 4]>::as_ref(&inv_view_proj)),
+        );
+
+        let mut encoder =
+            self.graphics_core
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Skybox Encoder"),
+                });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Skybox Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: surface_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &window_surface.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        self.graphics_core
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hdr_bytes(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [f32; 3]) -> Vec<u8> {
+        let pixels: Vec<image::Rgb<f32>> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| image::Rgb(pixel(x, y)))
+            .collect();
+        let mut bytes = Vec::new();
+        image::codecs::hdr::HdrEncoder::new(&mut bytes)
+            .encode(&pixels, width as usize, height as usize)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn rotation_only_drops_translation() {
+        let view = Matrix4::look_at_rh(
+            point3(3.0, 4.0, 5.0),
+            point3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        );
+        let rotation = Skybox::rotation_only(view);
+
+        let direction = vec4(0.0, 0.0, -1.0, 0.0);
+        assert_eq!(rotation * direction, view * direction);
+        assert_eq!(rotation * vec4(0.0, 0.0, 0.0, 1.0), vec4(0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hdr_sky_keeps_brightness() {
+        //Bright sky over dim ground.
+        let bytes = hdr_bytes(8, 4, |_, y| if y < 2 { [2.5; 3] } else { [0.5; 3] });
+        let faces = equirectangular_to_cube(&decode_hdr(&bytes).unwrap(), 4);
+        assert_eq!(validate_cube_faces(&faces, 8).unwrap(), 4);
+
+        //+Y and -Y faces.
+        assert_eq!(*faces[2].get_pixel(2, 2), image::Rgba([2.5, 2.5, 2.5, 1.0]));
+        assert_eq!(*faces[3].get_pixel(2, 2), image::Rgba([0.5, 0.5, 0.5, 1.0]));
+    }
+
+    #[test]
+    fn invalid_hdr_sky() {
+        assert!(decode_hdr(b"not an hdr image").is_err());
+
+        let image = decode_hdr(&hdr_bytes(8, 4, |_, _| [1.0; 3])).unwrap();
+        assert!(validate_cube_faces(&equirectangular_to_cube(&image, 0), 8).is_err());
+        assert!(validate_cube_faces(&equirectangular_to_cube(&image, 16), 8).is_err());
+
+        let mut mismatched = equirectangular_to_cube(&image, 4);
+        mismatched[1] = HdrImage::new(2, 2);
+        assert!(validate_cube_faces(&mismatched, 8).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use cgmath::*;
+
 use winit::event::ElementState;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -15,11 +17,36 @@ impl From<ElementState> for ButtonState {
     }
 }
 
+///How presses in a row are counted as double or triple clicks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClickSettings {
+    ///Max seconds from the previous press.
+    pub interval: f64,
+    ///Max pixels from the previous press. Only for buttons with cursor like mouse buttons.
+    pub slop: f32,
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        Self {
+            interval: 0.5,
+            slop: 4.0,
+        }
+    }
+}
+
 pub struct Buttons {
     signal: Vec<bool>,
     current: Vec<ButtonState>,
     before: Vec<ButtonState>,
     size: usize,
+
+    repeated: Vec<bool>,
+    pressed_at: Vec<f64>,
+    click_counts: Vec<u32>,
+    click_positions: Vec<Option<Point2<f32>>>,
+    time: f64,
+    click_settings: ClickSettings,
 }
 
 impl Buttons {
@@ -29,6 +56,13 @@ impl Buttons {
             current: vec![ButtonState::Released; size],
             before: vec![ButtonState::Released; size],
             size: size,
+
+            repeated: vec![false; size],
+            pressed_at: vec![0.0; size],
+            click_counts: vec![0; size],
+            click_positions: vec![None; size],
+            time: 0.0,
+            click_settings: ClickSettings::default(),
         }
     }
 
@@ -36,16 +70,45 @@ impl Buttons {
         &mut self,
         index: impl Into<usize> + Copy,
         state: impl Into<ButtonState>,
+    ) {
+        self.handle_input_at(index, state, None);
+    }
+
+    ///position is of cursor, to tell clicks at different places.
+    pub(crate) fn handle_input_at(
+        &mut self,
+        index: impl Into<usize> + Copy,
+        state: impl Into<ButtonState>,
+        position: Option<Point2<f32>>,
     ) {
         let index = index.into();
         if index >= self.size {
-            self.size = index + 1;
-            self.signal.resize(self.size, false);
-            self.current.resize(self.size, ButtonState::Released);
-            self.before.resize(self.size, ButtonState::Released);
+            self.resize(index + 1);
+        }
+        let state = state.into();
+        match (self.current[index], state) {
+            (ButtonState::Pressed, ButtonState::Pressed) => self.repeated[index] = true,
+            (ButtonState::Released, ButtonState::Pressed) => {
+                let in_time = self.click_counts[index] > 0
+                    && self.time - self.pressed_at[index] <= self.click_settings.interval;
+                let in_place = match (self.click_positions[index], position) {
+                    (Some(previous), Some(position)) => {
+                        previous.distance(position) <= self.click_settings.slop
+                    }
+                    _ => true,
+                };
+                self.click_counts[index] = if in_time && in_place {
+                    self.click_counts[index] + 1
+                } else {
+                    1
+                };
+                self.pressed_at[index] = self.time;
+                self.click_positions[index] = position;
+            }
+            _ => {}
         }
         self.signal[index] = true;
-        self.current[index] = state.into();
+        self.current[index] = state;
     }
 
     pub fn is_signaled(&self, index: impl Into<usize> + Copy) -> bool {
@@ -57,12 +120,7 @@ impl Buttons {
     }
 
     pub fn are_signaled(&self, indexes: &[impl Into<usize> + Copy]) -> bool {
-        match indexes.last() {
-            Some(index) if self.size > Into::<usize>::into(*index) => indexes
-                .iter()
-                .all(|index| self.signal[Into::<usize>::into(*index)]),
-            _ => false,
-        }
+        !indexes.is_empty() && indexes.iter().all(|index| self.is_signaled(*index))
     }
 
     pub fn is_pressed(&self, index: impl Into<usize> + Copy) -> bool {
@@ -74,12 +132,16 @@ impl Buttons {
     }
 
     pub fn are_pressed(&self, indexes: &[impl Into<usize> + Copy]) -> bool {
-        match indexes.last() {
-            Some(index) if self.size > Into::<usize>::into(*index) => indexes
-                .iter()
-                .all(|index| self.current[Into::<usize>::into(*index)] == ButtonState::Pressed),
-            _ => false,
+        !indexes.is_empty() && indexes.iter().all(|index| self.is_pressed(*index))
+    }
+
+    ///Whether it was pressed at the previous frame.
+    pub fn was_pressed(&self, index: impl Into<usize> + Copy) -> bool {
+        let index = index.into();
+        if index >= self.size {
+            return false;
         }
+        self.before[index] == ButtonState::Pressed
     }
 
     pub fn is_released(&self, index: impl Into<usize> + Copy) -> bool {
@@ -91,12 +153,7 @@ impl Buttons {
     }
 
     pub fn are_released(&self, indexes: &[impl Into<usize> + Copy]) -> bool {
-        match indexes.last() {
-            Some(index) if self.size > Into::<usize>::into(*index) => indexes
-                .iter()
-                .all(|index| self.current[Into::<usize>::into(*index)] == ButtonState::Released),
-            _ => false,
-        }
+        !indexes.is_empty() && indexes.iter().all(|index| self.is_released(*index))
     }
 
     pub fn is_just_pressed(&self, index: impl Into<usize> + Copy) -> bool {
@@ -108,14 +165,7 @@ impl Buttons {
     }
 
     pub fn are_just_pressed(&self, indexes: &[impl Into<usize> + Copy]) -> bool {
-        match indexes.last() {
-            Some(index) if self.size > Into::<usize>::into(*index) => indexes.iter().all(|index| {
-                let index = Into::<usize>::into(*index);
-                self.current[index] == ButtonState::Pressed
-                    && self.before[index] == ButtonState::Released
-            }),
-            _ => false,
-        }
+        !indexes.is_empty() && indexes.iter().all(|index| self.is_just_pressed(*index))
     }
 
     pub fn is_just_released(&self, index: impl Into<usize> + Copy) -> bool {
@@ -127,27 +177,116 @@ impl Buttons {
     }
 
     pub fn are_just_released(&self, indexes: &[impl Into<usize> + Copy]) -> bool {
-        match indexes.last() {
-            Some(index) if self.size > Into::<usize>::into(*index) => indexes.iter().all(|index| {
-                let index = Into::<usize>::into(*index);
-                self.current[index] == ButtonState::Released
-                    && self.before[index] == ButtonState::Pressed
-            }),
-            _ => false,
+        !indexes.is_empty() && indexes.iter().all(|index| self.is_just_released(*index))
+    }
+}
+
+impl Buttons {
+    ///Seconds since pressed. 0 when released.
+    pub fn held_for(&self, index: impl Into<usize> + Copy) -> f64 {
+        let index = index.into();
+        if !self.is_pressed(index) {
+            return 0.0;
         }
+        self.time - self.pressed_at[index]
+    }
+
+    pub fn is_long_pressed(&self, index: impl Into<usize> + Copy, secs: f64) -> bool {
+        self.is_pressed(index) && self.held_for(index) >= secs
+    }
+
+    ///Presses in a row of the last press, e.g. 2 for double click. 0 when never pressed.
+    ///Check with is_just_pressed to get it once.
+    pub fn click_count(&self, index: impl Into<usize> + Copy) -> u32 {
+        let index = index.into();
+        if index >= self.size {
+            return 0;
+        }
+        self.click_counts[index]
+    }
+
+    ///Pressed again while held at this frame, by key repeat.
+    pub fn is_repeated(&self, index: impl Into<usize> + Copy) -> bool {
+        let index = index.into();
+        if index >= self.size {
+            return false;
+        }
+        self.repeated[index]
+    }
+
+    pub fn click_settings(&self) -> ClickSettings {
+        self.click_settings
     }
 }
 
 impl Buttons {
-    ///Updates buttons states before polling events
-    pub(crate) fn pre_update(&mut self) {
+    ///Updates buttons states before polling events. time is of this frame.
+    pub(crate) fn pre_update(&mut self, time: f64) {
         self.signal = vec![false; self.size];
+        self.repeated = vec![false; self.size];
         self.before.copy_from_slice(&self.current);
+        self.time = time;
+    }
+
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.click_settings = click_settings;
+    }
+
+    ///Pressed ones become just released, e.g. when releases can't be told anymore.
+    pub(crate) fn release_all(&mut self) {
+        for index in 0..self.size {
+            if self.current[index] == ButtonState::Pressed {
+                self.handle_input(index, ButtonState::Released);
+            }
+        }
+    }
+
+    pub(crate) fn time(&self) -> f64 {
+        self.time
     }
 
     pub(crate) fn resize(&mut self, new_size: usize) {
+        self.size = new_size;
         self.signal.resize(new_size, false);
         self.current.resize(new_size, ButtonState::Released);
         self.before.resize(new_size, ButtonState::Released);
+
+        self.repeated.resize(new_size, false);
+        self.pressed_at.resize(new_size, 0.0);
+        self.click_counts.resize(new_size, 0);
+        self.click_positions.resize(new_size, None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn are_pressed_checks_every_index() {
+        let mut buttons = Buttons::new(4);
+        buttons.handle_input(0usize, ButtonState::Pressed);
+        assert!(buttons.are_pressed(&[0usize]));
+        assert!(!buttons.are_pressed(&[9usize, 0]));
+        assert!(!buttons.are_just_pressed(&[9usize, 0]));
+        assert!(!buttons.are_pressed(&[] as &[usize]));
+    }
+
+    #[test]
+    fn held_and_repeated() {
+        let mut buttons = Buttons::new(4);
+        buttons.pre_update(1.0);
+        buttons.handle_input(1usize, ButtonState::Pressed);
+        buttons.pre_update(1.75);
+        buttons.handle_input(1usize, ButtonState::Pressed);
+        assert_eq!(buttons.held_for(1usize), 0.75);
+        assert!(buttons.is_long_pressed(1usize, 0.5));
+        assert!(buttons.is_repeated(1usize));
+        assert!(!buttons.is_just_pressed(1usize));
+
+        buttons.pre_update(2.0);
+        buttons.handle_input(1usize, ButtonState::Released);
+        assert!(!buttons.is_repeated(1usize));
+        assert_eq!(buttons.held_for(1usize), 0.0);
     }
 }
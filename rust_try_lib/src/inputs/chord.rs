@@ -0,0 +1,255 @@
+//!Key chords like "Control+Shift+S" and sequences of them like "G G".
+use super::*;
+
+use std::{fmt, str::FromStr};
+
+use crate::utils::Time;
+
+///Key pressed with modifiers which were held before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub key: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyPress {
+    pub fn matches(&self, chord: KeyChord) -> bool {
+        let (key, modifiers) = Self::normalize(self.key, self.modifiers);
+        let (chord_key, chord_modifiers) = Self::normalize(chord.key, chord.modifiers);
+        key == chord_key && modifiers == chord_modifiers
+    }
+
+    ///Left and right modifier keys become same, and don't modify themselves.
+    fn normalize(key: KeyCode, mut modifiers: Modifiers) -> (KeyCode, Modifiers) {
+        match key.modifier() {
+            Some(modifier) => {
+                modifiers.remove(modifier);
+                let key = match key {
+                    KeyCode::RShift => KeyCode::LShift,
+                    KeyCode::RControl => KeyCode::LControl,
+                    KeyCode::RAlt => KeyCode::LAlt,
+                    KeyCode::RWin => KeyCode::LWin,
+                    key => key,
+                };
+                (key, modifiers)
+            }
+            None => (key, modifiers),
+        }
+    }
+}
+
+///Modifiers must be exactly same, so Control+S doesn't match Control+Shift+S.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub modifiers: Modifiers,
+    pub key: KeyCode,
+}
+
+impl KeyChord {
+    pub fn new(modifiers: Modifiers, key: KeyCode) -> Self {
+        Self { modifiers, key }
+    }
+
+    pub fn is_just_pressed(&self, key_presses: &[KeyPress]) -> bool {
+        key_presses.iter().any(|key_press| key_press.matches(*self))
+    }
+}
+
+impl From<KeyCode> for KeyChord {
+    fn from(key: KeyCode) -> Self {
+        Self::new(Modifiers::NONE, key)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.is_empty() {
+            write!(f, "{}", self.key)
+        } else {
+            write!(f, "{}+{}", self.modifiers, self.key)
+        }
+    }
+}
+
+///e.g. "Control+Shift+S". Key comes last.
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match s.rfind('+') {
+            Some(index) => (&s[..index], &s[index + 1..]),
+            None => ("", s),
+        };
+        let key = key.trim();
+        Ok(Self {
+            modifiers: modifiers.parse()?,
+            key: key.parse().map_err(|_| format!("unknown key `{}`", key))?,
+        })
+    }
+}
+
+///Chords pressed one after another, each within timeout seconds from the previous one.
+///Modifier keys pressed between chords are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySequence {
+    chords: Vec<KeyChord>,
+    timeout: f64,
+
+    progress: usize,
+    last_time: f64,
+}
+
+impl KeySequence {
+    pub fn new(chords: Vec<KeyChord>) -> Self {
+        Self {
+            chords,
+            timeout: 1.0,
+
+            progress: 0,
+            last_time: 0.0,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: f64) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn chords(&self) -> &[KeyChord] {
+        &self.chords
+    }
+
+    pub fn timeout(&self) -> f64 {
+        self.timeout
+    }
+
+    ///Number of chords matched so far.
+    pub fn progress(&self) -> usize {
+        self.progress
+    }
+
+    pub fn reset(&mut self) {
+        self.progress = 0;
+    }
+
+    ///Call once per frame. True at the frame the last chord is pressed.
+    pub fn update(&mut self, key_presses: &[KeyPress], time: &Time) -> bool {
+        if self.chords.is_empty() {
+            return false;
+        }
+        if self.progress > 0 && time.real_time() - self.last_time > self.timeout {
+            self.progress = 0;
+        }
+
+        let mut completed = false;
+        for key_press in key_presses {
+            if key_press.matches(self.chords[self.progress]) {
+                self.progress += 1;
+            } else if key_press.key.modifier().is_some() {
+                continue;
+            } else if key_press.matches(self.chords[0]) {
+                self.progress = 1;
+            } else {
+                self.progress = 0;
+                continue;
+            }
+            self.last_time = time.real_time();
+
+            if self.progress == self.chords.len() {
+                self.progress = 0;
+                completed = true;
+            }
+        }
+        completed
+    }
+}
+
+///e.g. "G G" or "Control+K Control+C".
+impl FromStr for KeySequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chords = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<KeyChord>, String>>()?;
+        if chords.is_empty() {
+            return Err("empty sequence".to_string());
+        }
+        Ok(Self::new(chords))
+    }
+}
+
+impl Inputs {
+    ///Pressed in any window.
+    pub fn is_chord_just_pressed(&self, chord: KeyChord) -> bool {
+        self.window_inputs()
+            .any(|window_input| chord.is_just_pressed(window_input.key_presses()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::utils::ManualClock;
+    use winit::window::WindowId;
+
+    #[test]
+    fn chord_order_and_sides() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        let save: KeyChord = "Control+Shift+S".parse().unwrap();
+        let quick_save: KeyChord = "Control+S".parse().unwrap();
+
+        inputs.simulate_key(window_id, KeyCode::S, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::RControl, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::LShift, ButtonState::Pressed);
+        assert!(!inputs.is_chord_just_pressed(save));
+
+        inputs.advance_frame();
+        inputs.simulate_key(window_id, KeyCode::S, ButtonState::Released);
+        inputs.simulate_key(window_id, KeyCode::S, ButtonState::Pressed);
+        assert!(inputs.is_chord_just_pressed(save));
+        assert!(!inputs.is_chord_just_pressed(quick_save));
+
+        assert!(KeyPress {
+            key: KeyCode::RShift,
+            modifiers: Modifiers::SHIFT,
+        }
+        .matches(KeyCode::LShift.into()));
+        assert_eq!(save.to_string(), "Shift+Control+S");
+    }
+
+    #[test]
+    fn modifiers_changed() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_modifiers(window_id, Modifiers::CONTROL);
+        inputs.simulate_key(window_id, KeyCode::S, ButtonState::Pressed);
+        assert!(inputs.is_chord_just_pressed(KeyChord::new(Modifiers::CONTROL, KeyCode::S)));
+        assert_eq!(inputs.window_modifiers(window_id), Some(Modifiers::CONTROL));
+    }
+
+    #[test]
+    fn sequence_timeout() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        let mut time = Time::with_clock(ManualClock::new());
+        let mut sequence: KeySequence = "G G".parse().unwrap();
+        let mut press_g = |inputs: &mut Inputs, time: &mut Time, delta: f64| {
+            inputs.advance_frame();
+            time.advance(delta);
+            inputs.simulate_key(window_id, KeyCode::G, ButtonState::Released);
+            inputs.simulate_key(window_id, KeyCode::G, ButtonState::Pressed);
+            sequence.update(inputs.key_presses(window_id).unwrap(), time)
+        };
+
+        assert!(!press_g(&mut inputs, &mut time, 0.1));
+        assert!(press_g(&mut inputs, &mut time, 0.5));
+        assert!(!press_g(&mut inputs, &mut time, 0.1));
+        assert!(!press_g(&mut inputs, &mut time, 2.0));
+        assert!(press_g(&mut inputs, &mut time, 0.1));
+    }
+}
@@ -1,13 +1,52 @@
-use super::inputs::DeviceType;
+use super::inputs::{DeviceType, Inputs};
 
 use cgmath::*;
 
-use winit::event::*;
+use winit::{
+    event::*,
+    window::{CursorIcon, WindowId},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorMode {
+    Normal,
+    ///Invisible over the window.
+    Hidden,
+    ///Kept inside the window.
+    Confined,
+    ///Hidden and locked, for FPS style cameras. Motion comes from mouse devices.
+    Relative,
+}
+
+impl CursorMode {
+    pub fn is_grabbed(self) -> bool {
+        matches!(self, CursorMode::Confined | CursorMode::Relative)
+    }
+
+    pub fn is_visible(self) -> bool {
+        matches!(self, CursorMode::Normal | CursorMode::Confined)
+    }
+}
+
+///Applied to windows by the application after update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CursorRequest {
+    Mode(CursorMode),
+    Icon(CursorIcon),
+}
 
 pub struct Cursor {
     position: Point2<f32>,
     entered: bool,
     prev_entered: bool,
+
+    motion: Vector2<f32>,
+    ///Whether position is known since entered, so motion doesn't jump.
+    tracking: bool,
+    window_size: Vector2<f32>,
+
+    mode: CursorMode,
+    icon: CursorIcon,
 }
 
 impl Cursor {
@@ -16,13 +55,65 @@ impl Cursor {
             position: point2(0.0, 0.0),
             entered: false,
             prev_entered: false,
+
+            motion: Vector2::zero(),
+            tracking: false,
+            window_size: Vector2::zero(),
+
+            mode: CursorMode::Normal,
+            icon: CursorIcon::Default,
         }
     }
 
+    ///In pixels from top left of the window.
     pub fn position(&self) -> Point2<f32> {
         self.position
     }
 
+    ///0 to 1 from top left of the window.
+    pub fn normalized_position(&self) -> Point2<f32> {
+        if self.window_size.x <= 0.0 || self.window_size.y <= 0.0 {
+            return point2(0.0, 0.0);
+        }
+        point2(
+            self.position.x / self.window_size.x,
+            self.position.y / self.window_size.y,
+        )
+    }
+
+    ///-1 to 1 with y up, same as clip space.
+    pub fn ndc_position(&self) -> Point2<f32> {
+        let normalized = self.normalized_position();
+        point2(normalized.x * 2.0 - 1.0, 1.0 - normalized.y * 2.0)
+    }
+
+    ///Point under the cursor at depth of clip space, which is 0 to 1 in wgpu.
+    ///None when view_proj can't be inverted.
+    pub fn world_position(&self, view_proj: Matrix4<f32>, depth: f32) -> Option<Point3<f32>> {
+        let ndc = self.ndc_position();
+        let world = view_proj.invert()? * vec4(ndc.x, ndc.y, depth, 1.0);
+        if world.w.abs() <= f32::EPSILON {
+            return None;
+        }
+        Some(Point3::from_homogeneous(world))
+    }
+
+    ///Origin on near plane and normalized direction of the ray under the cursor.
+    pub fn world_ray(&self, view_proj: Matrix4<f32>) -> Option<(Point3<f32>, Vector3<f32>)> {
+        let near = self.world_position(view_proj, 0.0)?;
+        let far = self.world_position(view_proj, 1.0)?;
+        Some((near, (far - near).normalize()))
+    }
+
+    ///Moved pixels at this frame. Zero in relative mode, where Inputs::pointer_motion is used.
+    pub fn motion(&self) -> Vector2<f32> {
+        self.motion
+    }
+
+    pub fn window_size(&self) -> Vector2<f32> {
+        self.window_size
+    }
+
     pub fn is_entered(&self) -> bool {
         self.entered
     }
@@ -34,11 +125,32 @@ impl Cursor {
     pub fn is_just_left(&self) -> bool {
         !self.entered && self.prev_entered
     }
+
+    ///Applied mode. Requested one is applied after update.
+    pub fn mode(&self) -> CursorMode {
+        self.mode
+    }
+
+    pub fn icon(&self) -> CursorIcon {
+        self.icon
+    }
 }
 
 impl Cursor {
     pub(crate) fn pre_update(&mut self) {
         self.prev_entered = self.entered;
+        self.motion.set_zero();
+    }
+
+    pub(crate) fn resize(&mut self, window_size: Vector2<f32>) {
+        self.window_size = window_size;
+    }
+
+    pub(crate) fn handle_request(&mut self, request: CursorRequest) {
+        match request {
+            CursorRequest::Mode(mode) => self.mode = mode,
+            CursorRequest::Icon(icon) => self.icon = icon,
+        }
     }
 
     pub(crate) fn handle_input(&mut self, input: WindowEvent) -> Option<(DeviceId, DeviceType)> {
@@ -48,19 +160,116 @@ impl Cursor {
                 position,
                 ..
             } => {
-                self.position.x = position.x as f32;
-                self.position.y = position.y as f32;
+                let position = point2(position.x as f32, position.y as f32);
+                if self.tracking && self.mode != CursorMode::Relative {
+                    self.motion += position - self.position;
+                }
+                self.position = position;
+                self.tracking = true;
                 Some((device_id, DeviceType::Mouse))
             }
             WindowEvent::CursorEntered { device_id } => {
                 self.entered = true;
+                self.tracking = false;
                 Some((device_id, DeviceType::Mouse))
             }
             WindowEvent::CursorLeft { device_id } => {
                 self.entered = false;
+                self.tracking = false;
                 Some((device_id, DeviceType::Mouse))
             }
             _ => None,
         }
     }
 }
+
+impl Inputs {
+    ///Applied to the window after update. Cursor::mode tells it's applied.
+    ///When it fails, the mode stays and the scene is told by Scene::cursor_failed.
+    pub fn set_cursor_mode(&self, window_id: WindowId, mode: CursorMode) {
+        self.cursor_requests
+            .borrow_mut()
+            .push((window_id, CursorRequest::Mode(mode)));
+    }
+
+    pub fn set_cursor_icon(&self, window_id: WindowId, icon: CursorIcon) {
+        self.cursor_requests
+            .borrow_mut()
+            .push((window_id, CursorRequest::Icon(icon)));
+    }
+
+    ///Cursor motion, or motion of the primary mouse in relative mode.
+    pub fn pointer_motion(&self, window_id: WindowId) -> Vector2<f32> {
+        match self.cursor(window_id) {
+            Some(cursor) if cursor.mode() == CursorMode::Relative => self
+                .device_mouse(None)
+                .map_or(Vector2::zero(), |mouse| mouse.motion()),
+            Some(cursor) => cursor.motion(),
+            None => Vector2::zero(),
+        }
+    }
+
+    pub(crate) fn take_cursor_requests(&mut self) -> Vec<(WindowId, CursorRequest)> {
+        self.cursor_requests.get_mut().drain(..).collect()
+    }
+
+    ///Called once the request is applied to the window.
+    pub(crate) fn handle_cursor_request(&mut self, window_id: WindowId, request: CursorRequest) {
+        self.window_input_mut(window_id)
+            .cursor_mut()
+            .handle_request(request);
+    }
+
+    pub(crate) fn resize(&mut self, window_id: WindowId, size: winit::dpi::PhysicalSize<u32>) {
+        self.window_input_mut(window_id)
+            .cursor_mut()
+            .resize(vec2(size.width as f32, size.height as f32));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn motion_and_world_ray() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.resize(window_id, winit::dpi::PhysicalSize::new(200, 100));
+
+        inputs.simulate_cursor_enter(window_id);
+        inputs.simulate_cursor_move(window_id, point2(40.0, 40.0));
+        inputs.simulate_cursor_move(window_id, point2(100.0, 50.0));
+        let cursor = inputs.cursor(window_id).unwrap();
+        assert_eq!(cursor.motion(), vec2(60.0, 10.0));
+        assert_eq!(cursor.normalized_position(), point2(0.5, 0.5));
+        assert_eq!(cursor.ndc_position(), point2(0.0, 0.0));
+
+        let view_proj = perspective(Deg(90.0), 2.0, 1.0, 10.0)
+            * Matrix4::look_to_rh(point3(0.0, 0.0, 0.0), -Vector3::unit_z(), Vector3::unit_y());
+        let (origin, direction) = cursor.world_ray(view_proj).unwrap();
+        assert!(origin.x.abs() < 1e-4 && origin.y.abs() < 1e-4);
+        assert!((direction - -Vector3::unit_z()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn relative_motion() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.set_cursor_mode(window_id, CursorMode::Relative);
+        assert!(inputs.cursor(window_id).is_none());
+        for (window_id, request) in inputs.take_cursor_requests() {
+            inputs.handle_cursor_request(window_id, request);
+        }
+        assert_eq!(
+            inputs.cursor(window_id).unwrap().mode(),
+            CursorMode::Relative
+        );
+        inputs.simulate_cursor_move(window_id, point2(1.0, 1.0));
+        inputs.simulate_cursor_move(window_id, point2(5.0, 1.0));
+        inputs.simulate_mouse_motion(vec2(3.0, -2.0));
+        assert_eq!(inputs.cursor(window_id).unwrap().motion(), Vector2::zero());
+        assert_eq!(inputs.pointer_motion(window_id), vec2(3.0, -2.0));
+    }
+}
@@ -0,0 +1,404 @@
+use super::{buttons::*, inputs::InputDeviceId};
+
+use cgmath::*;
+
+use winit::event::*;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    ///A on Xbox, Cross on PlayStation.
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Mode,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+impl From<GamepadButton> for usize {
+    fn from(button: GamepadButton) -> Self {
+        button as usize
+    }
+}
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    ///Right is positive.
+    LeftStickX,
+    ///Up is positive.
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    ///0 to 1.
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    pub fn is_trigger(self) -> bool {
+        matches!(self, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger)
+    }
+}
+
+///Applied to every gamepad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadSettings {
+    ///Radial for sticks, linear for triggers.
+    pub dead_zone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self { dead_zone: 0.15 }
+    }
+}
+
+///Gamepad input from the backend or a replay, already mapped by the backend.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadInput {
+    Connected,
+    Disconnected,
+    Button {
+        button: GamepadButton,
+        state: ElementState,
+    },
+    ///Sticks are -1 to 1 and triggers 0 to 1, without dead zone.
+    Axis {
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+pub struct Gamepad {
+    buttons: Buttons,
+    axes: [f32; GamepadAxis::ALL.len()],
+
+    settings: GamepadSettings,
+}
+
+impl Gamepad {
+    pub fn new(settings: GamepadSettings) -> Self {
+        Self {
+            buttons: Buttons::new(GamepadButton::ALL.len()),
+            axes: [0.0; GamepadAxis::ALL.len()],
+
+            settings,
+        }
+    }
+
+    ///Dead zone applied. Stick axes use the radial dead zone of their stick.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        match axis {
+            GamepadAxis::LeftStickX => self.left_stick().x,
+            GamepadAxis::LeftStickY => self.left_stick().y,
+            GamepadAxis::RightStickX => self.right_stick().x,
+            GamepadAxis::RightStickY => self.right_stick().y,
+            _ => {
+                let value = self.axes[axis as usize];
+                if value < self.settings.dead_zone {
+                    0.0
+                } else {
+                    (value - self.settings.dead_zone) / (1.0 - self.settings.dead_zone)
+                }
+            }
+        }
+    }
+
+    ///Without dead zone.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> Vector2<f32> {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> Vector2<f32> {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    pub fn settings(&self) -> GamepadSettings {
+        self.settings
+    }
+
+    fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> Vector2<f32> {
+        let value = vec2(self.axes[x as usize], self.axes[y as usize]);
+        let magnitude = value.magnitude();
+        if magnitude < self.settings.dead_zone || magnitude <= f32::EPSILON {
+            return Vector2::zero();
+        }
+        //Rescaled so it starts from 0 at the edge of dead zone.
+        let rescaled =
+            ((magnitude - self.settings.dead_zone) / (1.0 - self.settings.dead_zone)).min(1.0);
+        value * (rescaled / magnitude)
+    }
+}
+
+impl Gamepad {
+    pub(crate) fn pre_update(&mut self, time: f64) {
+        self.buttons.pre_update(time);
+    }
+
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.buttons.set_click_settings(click_settings);
+    }
+
+    pub(crate) fn set_settings(&mut self, settings: GamepadSettings) {
+        self.settings = settings;
+    }
+
+    ///Connections are handled by the device inputs.
+    pub(crate) fn handle_input(&mut self, input: GamepadInput) {
+        match input {
+            GamepadInput::Button { button, state } => self.buttons.handle_input(button, state),
+            GamepadInput::Axis { axis, value } => {
+                let (min, max) = if axis.is_trigger() {
+                    (0.0, 1.0)
+                } else {
+                    (-1.0, 1.0)
+                };
+                self.axes[axis as usize] = value.clamp(min, max);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl std::ops::Deref for Gamepad {
+    type Target = Buttons;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buttons
+    }
+}
+
+///Polls gilrs, which knows gamepads and maps their buttons and axes.
+#[cfg(feature = "gamepad")]
+pub(crate) struct GamepadBackend {
+    gilrs: gilrs::Gilrs,
+    ///Gamepads connected before the backend, which gilrs doesn't tell by events.
+    connected: Vec<usize>,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadBackend {
+    ///None when gilrs can't start.
+    pub(crate) fn new() -> Option<Self> {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            //Works, just without gamepads.
+            Err(gilrs::Error::NotImplemented(gilrs)) => gilrs,
+            Err(_) => return None,
+        };
+        let mut connected: Vec<usize> = gilrs.gamepads().map(|(id, _)| id.into()).collect();
+        //Popped from the back, so the first stays primary.
+        connected.reverse();
+        Some(Self { gilrs, connected })
+    }
+
+    ///Gives gamepad ids of the backend with their inputs, until there's none left.
+    pub(crate) fn next_input(&mut self) -> Option<(usize, GamepadInput)> {
+        if let Some(id) = self.connected.pop() {
+            return Some((id, GamepadInput::Connected));
+        }
+        while let Some(event) = self.gilrs.next_event() {
+            if let Some(input) = Self::map_event(event.event) {
+                return Some((event.id.into(), input));
+            }
+        }
+        None
+    }
+
+    fn map_event(event: gilrs::EventType) -> Option<GamepadInput> {
+        use gilrs::{Axis, Button, EventType};
+
+        let button = |button| {
+            Some(match button {
+                Button::South => GamepadButton::South,
+                Button::East => GamepadButton::East,
+                Button::West => GamepadButton::West,
+                Button::North => GamepadButton::North,
+                Button::LeftTrigger => GamepadButton::LeftBumper,
+                Button::RightTrigger => GamepadButton::RightBumper,
+                Button::Select => GamepadButton::Select,
+                Button::Start => GamepadButton::Start,
+                Button::Mode => GamepadButton::Mode,
+                Button::LeftThumb => GamepadButton::LeftStick,
+                Button::RightThumb => GamepadButton::RightStick,
+                Button::DPadUp => GamepadButton::DPadUp,
+                Button::DPadDown => GamepadButton::DPadDown,
+                Button::DPadLeft => GamepadButton::DPadLeft,
+                Button::DPadRight => GamepadButton::DPadRight,
+                _ => return None,
+            })
+        };
+
+        match event {
+            EventType::Connected => Some(GamepadInput::Connected),
+            EventType::Disconnected => Some(GamepadInput::Disconnected),
+            EventType::ButtonPressed(pressed, _) => Some(GamepadInput::Button {
+                button: button(pressed)?,
+                state: ElementState::Pressed,
+            }),
+            EventType::ButtonReleased(released, _) => Some(GamepadInput::Button {
+                button: button(released)?,
+                state: ElementState::Released,
+            }),
+            //Triggers are analog buttons in gilrs.
+            EventType::ButtonChanged(Button::LeftTrigger2, value, _) => Some(GamepadInput::Axis {
+                axis: GamepadAxis::LeftTrigger,
+                value,
+            }),
+            EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                Some(GamepadInput::Axis {
+                    axis: GamepadAxis::RightTrigger,
+                    value,
+                })
+            }
+            EventType::AxisChanged(axis, value, _) => Some(GamepadInput::Axis {
+                axis: match axis {
+                    Axis::LeftStickX => GamepadAxis::LeftStickX,
+                    Axis::LeftStickY => GamepadAxis::LeftStickY,
+                    Axis::RightStickX => GamepadAxis::RightStickX,
+                    Axis::RightStickY => GamepadAxis::RightStickY,
+                    _ => return None,
+                },
+                value,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadEvent {
+    Connected(InputDeviceId),
+    Disconnected(InputDeviceId),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::inputs::Inputs;
+
+    fn button(button: GamepadButton, state: ElementState) -> GamepadInput {
+        GamepadInput::Button { button, state }
+    }
+
+    fn axis(axis: GamepadAxis, value: f32) -> GamepadInput {
+        GamepadInput::Axis { axis, value }
+    }
+
+    #[test]
+    fn stick_dead_zone() {
+        let mut gamepad = Gamepad::new(GamepadSettings::default());
+        gamepad.handle_input(axis(GamepadAxis::LeftStickX, 0.1));
+        assert_eq!(gamepad.left_stick(), Vector2::zero());
+        assert!(gamepad.raw_axis(GamepadAxis::LeftStickX) > 0.0);
+
+        gamepad.handle_input(axis(GamepadAxis::LeftStickY, 1.0));
+        assert!((gamepad.axis(GamepadAxis::LeftStickY) - 1.0).abs() < 0.01);
+
+        //Out of range values are clamped.
+        gamepad.handle_input(axis(GamepadAxis::LeftTrigger, 2.0));
+        assert_eq!(gamepad.axis(GamepadAxis::LeftTrigger), 1.0);
+        gamepad.handle_input(axis(GamepadAxis::LeftTrigger, 0.1));
+        assert_eq!(gamepad.axis(GamepadAxis::LeftTrigger), 0.0);
+    }
+
+    #[test]
+    fn buttons() {
+        let mut gamepad = Gamepad::new(GamepadSettings::default());
+        gamepad.handle_input(button(GamepadButton::North, ElementState::Pressed));
+        assert!(gamepad.is_just_pressed(GamepadButton::North));
+
+        gamepad.pre_update(0.0);
+        assert!(gamepad.is_pressed(GamepadButton::North));
+        assert!(!gamepad.is_just_pressed(GamepadButton::North));
+    }
+
+    #[test]
+    fn connected_and_disconnected() {
+        let mut inputs = Inputs::new();
+        let device_id = InputDeviceId::Gamepad(7);
+        inputs.handle_gamepad_input(7, GamepadInput::Connected);
+        inputs.handle_gamepad_input(7, button(GamepadButton::South, ElementState::Pressed));
+        assert_eq!(inputs.gamepad_events(), &[GamepadEvent::Connected(device_id)]);
+        assert!(inputs
+            .gamepad(None)
+            .unwrap()
+            .is_pressed(GamepadButton::South));
+
+        inputs.handle_gamepad_input(7, GamepadInput::Disconnected);
+        assert_eq!(
+            inputs.gamepad_events(),
+            &[
+                GamepadEvent::Connected(device_id),
+                GamepadEvent::Disconnected(device_id)
+            ]
+        );
+        assert!(inputs.gamepad(Some(device_id)).is_none());
+
+        inputs.pre_update();
+        assert!(inputs.gamepad_events().is_empty());
+    }
+
+    #[test]
+    fn device_motion_is_not_a_gamepad() {
+        let mut inputs = Inputs::new();
+        let device_id = unsafe { DeviceId::dummy() };
+        for _ in 0..4 {
+            inputs.handle_device_input(
+                device_id,
+                DeviceEvent::Motion {
+                    axis: 5,
+                    value: 255.0,
+                },
+            );
+            inputs.pre_update();
+        }
+        assert!(inputs.gamepad(None).is_none());
+        assert!(inputs.gamepad_events().is_empty());
+    }
+}
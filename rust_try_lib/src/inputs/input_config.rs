@@ -0,0 +1,455 @@
+//!Saves and loads InputMap as TOML. Each name has an array of binding tables.
+//!```toml
+//![[action.jump]]
+//!source = "Key:Space"
+//!modifiers = "Control+Shift"
+//!
+//![[axis.fly]]
+//!source = "Buttons(Key:LShift, Key:Space)"
+//!scale = 1.0
+//!dead_zone = 0.0
+//!
+//![[axis2.move]]
+//!x = "Buttons(Key:A, Key:D)"
+//!y = "MouseMotionY"
+//!scale = 1.0
+//!dead_zone = 0.0
+//!normalize = true
+//!```
+//!scale, dead_zone, normalize and modifiers can be left out.
+use super::*;
+
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug)]
+pub enum InputConfigError {
+    Io(std::io::Error),
+    ///Includes where it is in the text.
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for InputConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputConfigError::Io(error) => write!(f, "{}", error),
+            InputConfigError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for InputConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InputConfigError::Io(error) => Some(error),
+            InputConfigError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for InputConfigError {
+    fn from(error: std::io::Error) -> Self {
+        InputConfigError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for InputConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        InputConfigError::Parse(error)
+    }
+}
+
+impl fmt::Display for ButtonSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ButtonSource::Key(key) => write!(f, "Key:{}", key),
+            ButtonSource::PhysicalKey(key) => write!(f, "PhysicalKey:{}", key),
+            ButtonSource::Mouse(MouseButton::Other(index)) => write!(f, "Mouse:{}", index),
+            ButtonSource::Mouse(button) => write!(f, "Mouse:{:?}", button),
+            ButtonSource::DeviceButton(button) => write!(f, "Device:{}", button),
+            ButtonSource::GamepadButton(button) => write!(f, "Gamepad:{:?}", button),
+        }
+    }
+}
+
+///e.g. "Key:Space", "PhysicalKey:W", "Mouse:Left", "Mouse:4", "Device:0", "Gamepad:South".
+impl FromStr for ButtonSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || format!("unknown button `{}`", s);
+        let (kind, value) = s.trim().split_once(':').ok_or_else(unknown)?;
+        match kind {
+            "Key" => value.parse().map(ButtonSource::Key).map_err(|_| unknown()),
+            "PhysicalKey" => value
+                .parse()
+                .map(ButtonSource::PhysicalKey)
+                .map_err(|_| unknown()),
+            "Mouse" => Ok(ButtonSource::Mouse(match value {
+                "Left" => MouseButton::Left,
+                "Middle" => MouseButton::Middle,
+                "Right" => MouseButton::Right,
+                _ => MouseButton::Other(value.parse().map_err(|_| unknown())?),
+            })),
+            "Device" => value
+                .parse()
+                .map(ButtonSource::DeviceButton)
+                .map_err(|_| unknown()),
+            "Gamepad" => GamepadButton::ALL
+                .iter()
+                .find(|button| format!("{:?}", button) == value)
+                .map(|button| ButtonSource::GamepadButton(*button))
+                .ok_or_else(unknown),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl fmt::Display for AxisSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AxisSource::Buttons { negative, positive } => {
+                write!(f, "Buttons({}, {})", negative, positive)
+            }
+            AxisSource::MouseMotionX => write!(f, "MouseMotionX"),
+            AxisSource::MouseMotionY => write!(f, "MouseMotionY"),
+            AxisSource::MouseWheel => write!(f, "MouseWheel"),
+            AxisSource::MouseWheelX => write!(f, "MouseWheelX"),
+            AxisSource::DeviceAxis(axis) => write!(f, "DeviceAxis:{}", axis),
+            AxisSource::GamepadAxis(axis) => write!(f, "GamepadAxis:{:?}", axis),
+        }
+    }
+}
+
+///e.g. "Buttons(Key:A, Key:D)", "MouseWheel", "DeviceAxis:1", "GamepadAxis:LeftStickX".
+impl FromStr for AxisSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let unknown = || format!("unknown axis `{}`", s);
+        if let Some(buttons) = s
+            .strip_prefix("Buttons(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let (negative, positive) = buttons.split_once(',').ok_or_else(unknown)?;
+            return Ok(AxisSource::Buttons {
+                negative: negative.parse()?,
+                positive: positive.parse()?,
+            });
+        }
+        if let Some(axis) = s.strip_prefix("DeviceAxis:") {
+            return axis
+                .parse()
+                .map(AxisSource::DeviceAxis)
+                .map_err(|_| unknown());
+        }
+        if let Some(axis) = s.strip_prefix("GamepadAxis:") {
+            return GamepadAxis::ALL
+                .iter()
+                .find(|known| format!("{:?}", known) == axis)
+                .map(|axis| AxisSource::GamepadAxis(*axis))
+                .ok_or_else(unknown);
+        }
+        match s {
+            "MouseMotionX" => Ok(AxisSource::MouseMotionX),
+            "MouseMotionY" => Ok(AxisSource::MouseMotionY),
+            "MouseWheel" => Ok(AxisSource::MouseWheel),
+            "MouseWheelX" => Ok(AxisSource::MouseWheelX),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+///Sources are written as their Display, so configs stay readable.
+fn serialize_display<T: fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr<Err = String>,
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionEntry {
+    #[serde(
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    source: ButtonSource,
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    modifiers: Modifiers,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisEntry {
+    #[serde(
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    source: AxisSource,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default)]
+    dead_zone: f32,
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    modifiers: Modifiers,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Axis2Entry {
+    #[serde(
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    x: AxisSource,
+    #[serde(
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    y: AxisSource,
+    #[serde(default = "default_scale")]
+    scale: f32,
+    #[serde(default)]
+    dead_zone: f32,
+    #[serde(default)]
+    normalize: bool,
+    #[serde(
+        default,
+        skip_serializing_if = "is_default",
+        serialize_with = "serialize_display",
+        deserialize_with = "deserialize_from_str"
+    )]
+    modifiers: Modifiers,
+}
+
+///Whole file. Maps are sorted, so same map gives same text.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    action: BTreeMap<String, Vec<ActionEntry>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    axis: BTreeMap<String, Vec<AxisEntry>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    axis2: BTreeMap<String, Vec<Axis2Entry>>,
+}
+
+impl InputMap {
+    ///Names are sorted, so same map gives same text.
+    pub fn to_config(&self) -> String {
+        let mut config = Config::default();
+        let (actions, axes, axes2) = self.names();
+
+        for name in actions {
+            let entries = self
+                .action_bindings(name)
+                .iter()
+                .map(|binding| ActionEntry {
+                    source: binding.source,
+                    modifiers: binding.modifiers,
+                })
+                .collect();
+            config.action.insert(name.to_string(), entries);
+        }
+        for name in axes {
+            let entries = self
+                .axis_bindings(name)
+                .iter()
+                .map(|binding| AxisEntry {
+                    source: binding.source,
+                    scale: binding.scale,
+                    dead_zone: binding.dead_zone,
+                    modifiers: binding.modifiers,
+                })
+                .collect();
+            config.axis.insert(name.to_string(), entries);
+        }
+        for name in axes2 {
+            let entries = self
+                .axis2_bindings(name)
+                .iter()
+                .map(|binding| Axis2Entry {
+                    x: binding.x,
+                    y: binding.y,
+                    scale: binding.scale,
+                    dead_zone: binding.dead_zone,
+                    normalize: binding.normalize,
+                    modifiers: binding.modifiers,
+                })
+                .collect();
+            config.axis2.insert(name.to_string(), entries);
+        }
+
+        //Only strings, numbers and booleans under arrays of tables, which TOML can always hold.
+        toml::to_string(&config).expect("input config is always valid TOML")
+    }
+
+    pub fn from_config(config: &str) -> Result<Self, InputConfigError> {
+        let config: Config = toml::from_str(config)?;
+        let mut input_map = InputMap::new();
+
+        for (name, entries) in config.action {
+            for entry in entries {
+                input_map.bind_action(
+                    &name,
+                    ActionBinding::new(entry.source).with_modifiers(entry.modifiers),
+                );
+            }
+        }
+        for (name, entries) in config.axis {
+            for entry in entries {
+                input_map.bind_axis(
+                    &name,
+                    AxisBinding::new(entry.source)
+                        .with_scale(entry.scale)
+                        .with_dead_zone(entry.dead_zone)
+                        .with_modifiers(entry.modifiers),
+                );
+            }
+        }
+        for (name, entries) in config.axis2 {
+            for entry in entries {
+                let mut binding = Axis2Binding::new(entry.x, entry.y)
+                    .with_scale(entry.scale)
+                    .with_dead_zone(entry.dead_zone)
+                    .with_modifiers(entry.modifiers);
+                binding.normalize = entry.normalize;
+                input_map.bind_axis2(&name, binding);
+            }
+        }
+
+        Ok(input_map)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputConfigError> {
+        std::fs::write(path, self.to_config())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputConfigError> {
+        Self::from_config(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> InputMap {
+        let mut input_map = InputMap::new();
+        input_map
+            .bind_action("jump", ActionBinding::new(KeyCode::Space))
+            .bind_action(
+                "crouch",
+                ActionBinding::new(ButtonSource::PhysicalKey(KeyCode::C)),
+            )
+            .bind_action(
+                "save",
+                ActionBinding::new(KeyCode::S)
+                    .with_modifiers(Modifiers::CONTROL | Modifiers::SHIFT),
+            )
+            .bind_action("fire", ActionBinding::new(MouseButton::Other(4)))
+            .bind_action("fire", ActionBinding::new(ButtonSource::DeviceButton(2)))
+            .bind_action("fire", ActionBinding::new(GamepadButton::RightBumper))
+            .bind_axis(
+                "throttle",
+                AxisBinding::new(AxisSource::GamepadAxis(GamepadAxis::RightTrigger)),
+            )
+            .bind_axis(
+                "zoom",
+                AxisBinding::new(AxisSource::MouseWheel).with_scale(0.5),
+            )
+            .bind_axis2(
+                "move",
+                Axis2Binding::composite(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+            )
+            .bind_axis2(
+                "look",
+                Axis2Binding::new(AxisSource::DeviceAxis(0), AxisSource::DeviceAxis(1))
+                    .with_dead_zone(0.2),
+            );
+        input_map
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = sample().to_config();
+        let loaded = InputMap::from_config(&config).unwrap();
+        assert_eq!(loaded.to_config(), config);
+        assert_eq!(
+            loaded.action_bindings("save"),
+            sample().action_bindings("save")
+        );
+        assert_eq!(
+            loaded.axis2_bindings("look"),
+            sample().axis2_bindings("look")
+        );
+    }
+
+    #[test]
+    fn names_with_escapes() {
+        let mut input_map = InputMap::new();
+        let name = "say \"hi\" \\ bye\n\u{301}é";
+        input_map.bind_action(name, ActionBinding::new(KeyCode::H));
+
+        let config = input_map.to_config();
+        //No Rust escapes, which TOML doesn't have.
+        assert!(!config.contains("\\u{"));
+        let loaded = InputMap::from_config(&config).unwrap();
+        assert_eq!(loaded.names().0, vec![name]);
+        assert_eq!(loaded.to_config(), config);
+        assert!(InputMap::from_config("[[action.\"bad\\q\"]]\nsource = \"Key:A\"").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        match InputMap::from_config("[[action.jump]]\nsource = \"Key:Nope\"\n") {
+            //Points the table it's in, counted from 0.
+            Err(InputConfigError::Parse(error)) => {
+                assert_eq!(error.line_col().map(|(line, _)| line), Some(0));
+                assert!(error.to_string().contains("Key:Nope"));
+            }
+            other => panic!("{:?}", other.map(|input_map| input_map.to_config())),
+        }
+        assert!(InputMap::from_config("source = \"Key:A\"").is_err());
+        assert!(InputMap::from_config("[[action.jump]]\nsource = \"Key:A\"\nfoo = 1").is_err());
+        //Left out values get defaults.
+        let loaded = InputMap::from_config("[[axis.zoom]]\nsource = \"MouseWheel\"").unwrap();
+        assert_eq!(
+            loaded.axis_bindings("zoom"),
+            &[AxisBinding::new(AxisSource::MouseWheel)]
+        );
+    }
+}
@@ -0,0 +1,727 @@
+//!Binds named actions and axes to any input sources.
+use super::*;
+
+use std::collections::HashMap;
+
+use winit::window::WindowId;
+
+use cgmath::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ButtonSource {
+    Key(KeyCode),
+    ///Key at the position of given key on a US layout, so WASD stays in place on AZERTY.
+    PhysicalKey(KeyCode),
+    Mouse(MouseButton),
+    ///Button of devices which are not classified yet.
+    DeviceButton(u32),
+    GamepadButton(GamepadButton),
+}
+
+impl From<KeyCode> for ButtonSource {
+    fn from(key: KeyCode) -> Self {
+        ButtonSource::Key(key)
+    }
+}
+
+impl From<GamepadButton> for ButtonSource {
+    fn from(button: GamepadButton) -> Self {
+        ButtonSource::GamepadButton(button)
+    }
+}
+
+impl From<MouseButton> for ButtonSource {
+    fn from(button: MouseButton) -> Self {
+        ButtonSource::Mouse(button)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisSource {
+    ///-1 when negative is pressed, 1 when positive is pressed.
+    Buttons {
+        negative: ButtonSource,
+        positive: ButtonSource,
+    },
+    MouseMotionX,
+    MouseMotionY,
+    ///Vertical lines.
+    MouseWheel,
+    ///Horizontal lines.
+    MouseWheelX,
+    ///Motion axis of devices which are not classified yet.
+    DeviceAxis(u32),
+    ///Dead zone of the gamepad applied.
+    GamepadAxis(GamepadAxis),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionBinding {
+    pub source: ButtonSource,
+    ///Should be held together. Extra modifiers are allowed.
+    pub modifiers: Modifiers,
+}
+
+impl ActionBinding {
+    pub fn new(source: impl Into<ButtonSource>) -> Self {
+        Self {
+            source: source.into(),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisBinding {
+    pub source: AxisSource,
+    pub scale: f32,
+    ///Absolute values below this are ignored.
+    pub dead_zone: f32,
+    pub modifiers: Modifiers,
+}
+
+impl AxisBinding {
+    pub fn new(source: AxisSource) -> Self {
+        Self {
+            source,
+            scale: 1.0,
+            dead_zone: 0.0,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn buttons(negative: impl Into<ButtonSource>, positive: impl Into<ButtonSource>) -> Self {
+        Self::new(AxisSource::Buttons {
+            negative: negative.into(),
+            positive: positive.into(),
+        })
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Axis2Binding {
+    pub x: AxisSource,
+    pub y: AxisSource,
+    pub scale: f32,
+    ///Radial. Vectors shorter than this are ignored.
+    pub dead_zone: f32,
+    ///Composite of buttons gets normalized so diagonal isn't faster.
+    pub normalize: bool,
+    pub modifiers: Modifiers,
+}
+
+impl Axis2Binding {
+    pub fn new(x: AxisSource, y: AxisSource) -> Self {
+        Self {
+            x,
+            y,
+            scale: 1.0,
+            dead_zone: 0.0,
+            normalize: false,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    ///e.g. WASD. y is positive at up.
+    pub fn composite(
+        up: impl Into<ButtonSource>,
+        down: impl Into<ButtonSource>,
+        left: impl Into<ButtonSource>,
+        right: impl Into<ButtonSource>,
+    ) -> Self {
+        let mut ret = Self::new(
+            AxisSource::Buttons {
+                negative: left.into(),
+                positive: right.into(),
+            },
+            AxisSource::Buttons {
+                negative: down.into(),
+                positive: up.into(),
+            },
+        );
+        ret.normalize = true;
+        ret
+    }
+
+    pub fn mouse_motion() -> Self {
+        Self::new(AxisSource::MouseMotionX, AxisSource::MouseMotionY)
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+///State of an action at this frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActionState {
+    pressed: bool,
+    was_pressed: bool,
+}
+
+impl ActionState {
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    pub fn is_released(&self) -> bool {
+        !self.pressed
+    }
+
+    pub fn is_just_pressed(&self) -> bool {
+        self.pressed && !self.was_pressed
+    }
+
+    pub fn is_just_released(&self) -> bool {
+        !self.pressed && self.was_pressed
+    }
+}
+
+///Named bindings. Several bindings of same name are combined.
+#[derive(Clone, Debug, Default)]
+pub struct InputMap {
+    actions: HashMap<String, Vec<ActionBinding>>,
+    axes: HashMap<String, Vec<AxisBinding>>,
+    axes2: HashMap<String, Vec<Axis2Binding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            axes2: HashMap::new(),
+        }
+    }
+
+    pub fn bind_action(&mut self, name: &str, binding: ActionBinding) -> &mut Self {
+        self.actions
+            .entry(name.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    pub fn bind_axis(&mut self, name: &str, binding: AxisBinding) -> &mut Self {
+        self.axes.entry(name.to_string()).or_default().push(binding);
+        self
+    }
+
+    pub fn bind_axis2(&mut self, name: &str, binding: Axis2Binding) -> &mut Self {
+        self.axes2
+            .entry(name.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+        self.axes2.remove(name);
+    }
+
+    pub fn clear(&mut self) {
+        self.actions.clear();
+        self.axes.clear();
+        self.axes2.clear();
+    }
+
+    pub fn action_bindings(&self, name: &str) -> &[ActionBinding] {
+        self.actions.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn axis_bindings(&self, name: &str) -> &[AxisBinding] {
+        self.axes.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn axis2_bindings(&self, name: &str) -> &[Axis2Binding] {
+        self.axes2.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    ///Sorted names of actions, axes and 2d axes.
+    pub fn names(&self) -> (Vec<&str>, Vec<&str>, Vec<&str>) {
+        (
+            sorted_names(self.actions.keys()),
+            sorted_names(self.axes.keys()),
+            sorted_names(self.axes2.keys()),
+        )
+    }
+
+    ///Names bound to the button with exactly same modifiers.
+    pub fn bound_to(&self, source: ButtonSource, modifiers: Modifiers) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .button_uses()
+            .filter(|(_, used_source, used_modifiers)| {
+                *used_source == source && *used_modifiers == modifiers
+            })
+            .map(|(name, _, _)| name)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    ///Buttons which trigger more than one name at once. Sorted by names.
+    ///Bindings match when held modifiers contain theirs, so S and Ctrl+S conflict on Ctrl+S.
+    pub fn conflicts(&self) -> Vec<BindingConflict> {
+        let mut uses: HashMap<ButtonSource, Vec<(Modifiers, &str)>> = HashMap::new();
+        for (name, source, modifiers) in self.button_uses() {
+            uses.entry(source).or_default().push((modifiers, name));
+        }
+
+        let mut conflicts: Vec<BindingConflict> = uses
+            .iter()
+            .flat_map(|(source, uses)| {
+                let mut held: Vec<Modifiers> = Vec::new();
+                for (modifiers, _) in uses {
+                    if !held.contains(modifiers) {
+                        held.push(*modifiers);
+                    }
+                }
+                held.into_iter().map(move |held| {
+                    let names: Vec<&str> = uses
+                        .iter()
+                        .filter(|(modifiers, _)| held.contains(*modifiers))
+                        .map(|(_, name)| *name)
+                        .collect();
+                    (*source, held, names)
+                })
+            })
+            .filter_map(|(source, modifiers, mut names)| {
+                names.sort_unstable();
+                names.dedup();
+                if names.len() < 2 {
+                    return None;
+                }
+                Some(BindingConflict {
+                    source,
+                    modifiers,
+                    names: names.into_iter().map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        conflicts.sort_by(|a, b| a.names.cmp(&b.names));
+        conflicts
+    }
+
+    fn button_uses(&self) -> impl Iterator<Item = (&str, ButtonSource, Modifiers)> {
+        let actions = self.actions.iter().flat_map(|(name, bindings)| {
+            bindings
+                .iter()
+                .map(move |binding| (name.as_str(), binding.source, binding.modifiers))
+        });
+        let axes = self.axes.iter().flat_map(|(name, bindings)| {
+            bindings.iter().flat_map(move |binding| {
+                axis_buttons(binding.source)
+                    .map(move |source| (name.as_str(), source, binding.modifiers))
+            })
+        });
+        let axes2 = self.axes2.iter().flat_map(|(name, bindings)| {
+            bindings.iter().flat_map(move |binding| {
+                axis_buttons(binding.x)
+                    .chain(axis_buttons(binding.y))
+                    .map(move |source| (name.as_str(), source, binding.modifiers))
+            })
+        });
+        actions.chain(axes).chain(axes2)
+    }
+}
+
+fn sorted_names<'a>(names: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+    let mut names: Vec<&str> = names.map(String::as_str).collect();
+    names.sort_unstable();
+    names
+}
+
+fn axis_buttons(source: AxisSource) -> impl Iterator<Item = ButtonSource> {
+    let buttons = match source {
+        AxisSource::Buttons { negative, positive } => vec![negative, positive],
+        _ => Vec::new(),
+    };
+    buttons.into_iter()
+}
+
+///Names triggered together by the button with the modifiers held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingConflict {
+    pub source: ButtonSource,
+    pub modifiers: Modifiers,
+    pub names: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Capture {
+    Idle,
+    Waiting,
+    Captured(ButtonSource),
+}
+
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() < dead_zone {
+        0.0
+    } else {
+        value
+    }
+}
+
+///Evaluation of sources. Buttons are pressed when pressed in any window.
+///Versions with _in only look at keys and mouse of the window. Devices count either way.
+impl Inputs {
+    pub fn action(&self, name: &str) -> ActionState {
+        self.scoped_action(None, name)
+    }
+
+    pub fn action_in(&self, window_id: WindowId, name: &str) -> ActionState {
+        self.scoped_action(Some(window_id), name)
+    }
+
+    ///Sum of bindings.
+    pub fn axis(&self, name: &str) -> f32 {
+        self.scoped_axis(None, name)
+    }
+
+    pub fn axis_in(&self, window_id: WindowId, name: &str) -> f32 {
+        self.scoped_axis(Some(window_id), name)
+    }
+
+    ///Sum of bindings.
+    pub fn axis2(&self, name: &str) -> Vector2<f32> {
+        self.scoped_axis2(None, name)
+    }
+
+    pub fn axis2_in(&self, window_id: WindowId, name: &str) -> Vector2<f32> {
+        self.scoped_axis2(Some(window_id), name)
+    }
+
+    fn scoped_action(&self, window_id: Option<WindowId>, name: &str) -> ActionState {
+        let modifiers = self.scoped_modifiers(window_id);
+        let mut state = ActionState::default();
+        for binding in self.input_map().action_bindings(name) {
+            if !modifiers.contains(binding.modifiers) {
+                continue;
+            }
+            state.pressed |= self.is_source_pressed(window_id, binding.source);
+            state.was_pressed |= self.was_source_pressed(window_id, binding.source);
+        }
+        state
+    }
+
+    fn scoped_axis(&self, window_id: Option<WindowId>, name: &str) -> f32 {
+        let modifiers = self.scoped_modifiers(window_id);
+        self.input_map()
+            .axis_bindings(name)
+            .iter()
+            .filter(|binding| modifiers.contains(binding.modifiers))
+            .map(|binding| {
+                apply_dead_zone(
+                    self.axis_source_value(window_id, binding.source),
+                    binding.dead_zone,
+                ) * binding.scale
+            })
+            .sum()
+    }
+
+    fn scoped_axis2(&self, window_id: Option<WindowId>, name: &str) -> Vector2<f32> {
+        let modifiers = self.scoped_modifiers(window_id);
+        self.input_map()
+            .axis2_bindings(name)
+            .iter()
+            .filter(|binding| modifiers.contains(binding.modifiers))
+            .map(|binding| {
+                let mut value = vec2(
+                    self.axis_source_value(window_id, binding.x),
+                    self.axis_source_value(window_id, binding.y),
+                );
+                let magnitude = value.magnitude();
+                if magnitude < binding.dead_zone || magnitude <= f32::EPSILON {
+                    return Vector2::zero();
+                }
+                if binding.normalize && magnitude > 1.0 {
+                    value /= magnitude;
+                }
+                value * binding.scale
+            })
+            .sum()
+    }
+
+    ///Next pressed button will be given by captured_button.
+    pub fn await_next_button(&self) {
+        self.capture.set(Capture::Waiting);
+    }
+
+    pub fn cancel_capture(&self) {
+        self.capture.set(Capture::Idle);
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.get() == Capture::Waiting
+    }
+
+    ///Some only at the frame it's pressed.
+    pub fn captured_button(&self) -> Option<ButtonSource> {
+        match self.capture.get() {
+            Capture::Captured(source) => Some(source),
+            _ => None,
+        }
+    }
+
+    pub(super) fn capture(&self, source: ButtonSource) {
+        if self.capture.get() == Capture::Waiting {
+            self.capture.set(Capture::Captured(source));
+        }
+    }
+
+    ///Modifiers held in any window.
+    pub fn modifiers(&self) -> Modifiers {
+        self.scoped_modifiers(None)
+    }
+
+    fn scoped_modifiers(&self, window_id: Option<WindowId>) -> Modifiers {
+        let mut modifiers = Modifiers::NONE;
+        for window_input in self.window_inputs_in(window_id) {
+            modifiers |= window_input.modifiers();
+        }
+        modifiers
+    }
+
+    fn is_source_pressed(&self, window_id: Option<WindowId>, source: ButtonSource) -> bool {
+        match source {
+            ButtonSource::Key(key) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.keyboard().is_pressed(key)),
+            ButtonSource::PhysicalKey(key) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.keyboard().is_physical_pressed(key)),
+            ButtonSource::Mouse(button) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.mouse().is_pressed(button)),
+            ButtonSource::DeviceButton(button) => self
+                .device_inputs()
+                .mocks()
+                .any(|mock| mock.is_pressed(button as usize)),
+            ButtonSource::GamepadButton(button) => self
+                .device_inputs()
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_pressed(button)),
+        }
+    }
+
+    fn was_source_pressed(&self, window_id: Option<WindowId>, source: ButtonSource) -> bool {
+        match source {
+            ButtonSource::Key(key) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.keyboard().was_pressed(key)),
+            ButtonSource::PhysicalKey(key) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.keyboard().was_physical_pressed(key)),
+            ButtonSource::Mouse(button) => self
+                .window_inputs_in(window_id)
+                .any(|window_input| window_input.mouse().was_pressed(button)),
+            ButtonSource::DeviceButton(button) => self
+                .device_inputs()
+                .mocks()
+                .any(|mock| mock.was_pressed(button as usize)),
+            ButtonSource::GamepadButton(button) => self
+                .device_inputs()
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.was_pressed(button)),
+        }
+    }
+
+    fn axis_source_value(&self, window_id: Option<WindowId>, source: AxisSource) -> f32 {
+        match source {
+            AxisSource::Buttons { negative, positive } => {
+                let value = |source| {
+                    if self.is_source_pressed(window_id, source) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                value(positive) - value(negative)
+            }
+            AxisSource::MouseMotionX => self
+                .device_mouse(None)
+                .map(|mouse| mouse.motion().x)
+                .unwrap_or(0.0),
+            AxisSource::MouseMotionY => self
+                .device_mouse(None)
+                .map(|mouse| mouse.motion().y)
+                .unwrap_or(0.0),
+            AxisSource::MouseWheel => self
+                .window_inputs_in(window_id)
+                .map(|window_input| window_input.mouse().wheel())
+                .sum(),
+            AxisSource::MouseWheelX => self
+                .window_inputs_in(window_id)
+                .map(|window_input| window_input.mouse().wheel_lines().x)
+                .sum(),
+            AxisSource::DeviceAxis(axis) => self
+                .device_inputs()
+                .mocks()
+                .filter_map(|mock| mock.motion().get(axis as usize))
+                .sum(),
+            AxisSource::GamepadAxis(axis) => self
+                .device_inputs()
+                .gamepads()
+                .map(|(_, gamepad)| gamepad.axis(axis))
+                .sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use winit::{event::*, window::WindowId};
+
+    #[allow(deprecated)]
+    fn press(inputs: &mut Inputs, key: VirtualKeyCode, state: ElementState) {
+        inputs.handle_window_input(
+            unsafe { WindowId::dummy() },
+            WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode: 0,
+                    state,
+                    virtual_keycode: Some(key),
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: false,
+            },
+        );
+    }
+
+    #[test]
+    fn action_states() {
+        let mut inputs = Inputs::new();
+        inputs
+            .input_map_mut()
+            .bind_action("jump", ActionBinding::new(KeyCode::Space))
+            .bind_action(
+                "save",
+                ActionBinding::new(KeyCode::S).with_modifiers(Modifiers::CONTROL),
+            );
+
+        press(&mut inputs, VirtualKeyCode::Space, ElementState::Pressed);
+        press(&mut inputs, VirtualKeyCode::S, ElementState::Pressed);
+        assert!(inputs.action("jump").is_just_pressed());
+        assert!(!inputs.action("save").is_pressed());
+
+        inputs.pre_update();
+        press(&mut inputs, VirtualKeyCode::LControl, ElementState::Pressed);
+        assert!(inputs.action("jump").is_pressed());
+        assert!(!inputs.action("jump").is_just_pressed());
+        assert!(inputs.action("save").is_pressed());
+
+        inputs.pre_update();
+        press(&mut inputs, VirtualKeyCode::Space, ElementState::Released);
+        assert!(inputs.action("jump").is_just_released());
+        assert!(inputs.action("unknown").is_released());
+    }
+
+    #[test]
+    fn composite_axis2() {
+        let mut inputs = Inputs::new();
+        inputs.input_map_mut().bind_axis2(
+            "move",
+            Axis2Binding::composite(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+        );
+
+        press(&mut inputs, VirtualKeyCode::W, ElementState::Pressed);
+        assert_eq!(inputs.axis2("move"), vec2(0.0, 1.0));
+        let window_id = unsafe { WindowId::dummy() };
+        assert_eq!(inputs.axis2_in(window_id, "move"), vec2(0.0, 1.0));
+
+        press(&mut inputs, VirtualKeyCode::D, ElementState::Pressed);
+        let diagonal = inputs.axis2("move");
+        assert!((diagonal.magnitude() - 1.0).abs() < 1e-6);
+        assert!(diagonal.x > 0.0 && diagonal.y > 0.0);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut input_map = InputMap::new();
+        input_map
+            .bind_action("jump", ActionBinding::new(KeyCode::Space))
+            .bind_action(
+                "save",
+                ActionBinding::new(KeyCode::S).with_modifiers(Modifiers::CONTROL),
+            )
+            .bind_axis("fly", AxisBinding::buttons(KeyCode::LShift, KeyCode::Space))
+            .bind_axis2(
+                "move",
+                Axis2Binding::composite(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+            );
+
+        let conflicts = input_map.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].source, ButtonSource::Key(KeyCode::Space));
+        assert_eq!(conflicts[0].names, vec!["fly", "jump"]);
+        //S of move fires on Ctrl+S as well.
+        assert_eq!(conflicts[1].source, ButtonSource::Key(KeyCode::S));
+        assert_eq!(conflicts[1].modifiers, Modifiers::CONTROL);
+        assert_eq!(conflicts[1].names, vec!["move", "save"]);
+        assert_eq!(
+            input_map.bound_to(KeyCode::S.into(), Modifiers::CONTROL),
+            vec!["save"]
+        );
+    }
+
+    #[test]
+    fn capture_next_button() {
+        let mut inputs = Inputs::new();
+        press(&mut inputs, VirtualKeyCode::A, ElementState::Pressed);
+        assert_eq!(inputs.captured_button(), None);
+
+        inputs.await_next_button();
+        assert!(inputs.is_capturing());
+        press(&mut inputs, VirtualKeyCode::A, ElementState::Released);
+        press(&mut inputs, VirtualKeyCode::Q, ElementState::Pressed);
+        press(&mut inputs, VirtualKeyCode::E, ElementState::Pressed);
+        assert_eq!(inputs.captured_button(), Some(KeyCode::Q.into()));
+
+        inputs.pre_update();
+        assert_eq!(inputs.captured_button(), None);
+        assert!(!inputs.is_capturing());
+    }
+
+    #[test]
+    fn dead_zone() {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-0.3, 0.2), -0.3);
+    }
+}
@@ -1,12 +1,26 @@
 use super::*;
 
-use std::collections::HashMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
 
 use winit::{event::*, window::WindowId};
 
 pub struct Inputs {
     window_inputs: HashMap<WindowId, WindowInput>,
     device_inputs: DeviceInputs,
+
+    input_map: InputMap,
+    pub(super) capture: Cell<Capture>,
+    pub(super) recorder: Option<InputRecorder>,
+    pub(super) clipboard: ClipboardCell,
+    pub(super) cursor_requests: RefCell<Vec<(WindowId, CursorRequest)>>,
+
+    time: f64,
+    click_settings: ClickSettings,
+    touch_settings: TouchSettings,
+    scroll_settings: ScrollSettings,
 }
 
 impl Inputs {
@@ -14,9 +28,64 @@ impl Inputs {
         Self {
             window_inputs: HashMap::new(),
             device_inputs: DeviceInputs::new(),
+
+            input_map: InputMap::new(),
+            capture: Cell::new(Capture::Idle),
+            recorder: None,
+            clipboard: ClipboardCell::new(Box::new(MemoryClipboard::default())),
+            cursor_requests: RefCell::new(Vec::new()),
+
+            time: 0.0,
+            click_settings: ClickSettings::default(),
+            touch_settings: TouchSettings::default(),
+            scroll_settings: ScrollSettings::default(),
         }
     }
 
+    pub fn input_map(&self) -> &InputMap {
+        &self.input_map
+    }
+
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input_map
+    }
+
+    pub(super) fn window_inputs(&self) -> impl Iterator<Item = &WindowInput> {
+        self.window_inputs.values()
+    }
+
+    ///Only of the window when given, or of every window.
+    pub(super) fn window_inputs_in(
+        &self,
+        window_id: Option<WindowId>,
+    ) -> impl Iterator<Item = &WindowInput> {
+        self.window_inputs
+            .iter()
+            .filter(move |(id, _)| window_id.is_none() || window_id == Some(**id))
+            .map(|(_, window_input)| window_input)
+    }
+
+    pub(super) fn device_inputs(&self) -> &DeviceInputs {
+        &self.device_inputs
+    }
+
+    pub(super) fn window_input_mut(&mut self, window_id: WindowId) -> &mut WindowInput {
+        let (time, click_settings, touch_settings, scroll_settings) = (
+            self.time,
+            self.click_settings,
+            self.touch_settings,
+            self.scroll_settings,
+        );
+        self.window_inputs.entry(window_id).or_insert_with(|| {
+            let mut window_input = WindowInput::new();
+            window_input.pre_update(time);
+            window_input.set_click_settings(click_settings);
+            window_input.set_touch_settings(touch_settings);
+            window_input.set_scroll_settings(scroll_settings);
+            window_input
+        })
+    }
+
     pub fn window_keyboard(&self, window_id: WindowId) -> Option<&Keyboard> {
         match self.window_inputs.get(&window_id) {
             Some(window_input) => Some(window_input.keyboard()),
@@ -24,10 +93,85 @@ impl Inputs {
         }
     }
 
-    pub fn device_keyboard(&self, device_id: Option<DeviceId>) -> Option<&Keyboard> {
+    pub fn device_keyboard(&self, device_id: Option<InputDeviceId>) -> Option<&Keyboard> {
         self.device_inputs.keyboard(device_id)
     }
 
+    ///None for the primary gamepad.
+    pub fn gamepad(&self, device_id: Option<InputDeviceId>) -> Option<&Gamepad> {
+        self.device_inputs.gamepad(device_id)
+    }
+
+    pub fn gamepad_events(&self) -> &[GamepadEvent] {
+        self.device_inputs.gamepad_events()
+    }
+
+    pub fn set_gamepad_settings(&mut self, settings: GamepadSettings) {
+        self.device_inputs.set_gamepad_settings(settings);
+    }
+
+    pub fn click_settings(&self) -> ClickSettings {
+        self.click_settings
+    }
+
+    ///Applied to every button of windows and devices.
+    pub fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        for window_input in self.window_inputs.values_mut() {
+            window_input.set_click_settings(click_settings);
+        }
+        self.device_inputs.set_click_settings(click_settings);
+        self.click_settings = click_settings;
+    }
+
+    pub fn touch_settings(&self) -> TouchSettings {
+        self.touch_settings
+    }
+
+    ///Applied to every window.
+    pub fn set_touch_settings(&mut self, touch_settings: TouchSettings) {
+        for window_input in self.window_inputs.values_mut() {
+            window_input.set_touch_settings(touch_settings);
+        }
+        self.touch_settings = touch_settings;
+    }
+
+    pub fn scroll_settings(&self) -> ScrollSettings {
+        self.scroll_settings
+    }
+
+    ///Applied to every window and mouse.
+    pub fn set_scroll_settings(&mut self, scroll_settings: ScrollSettings) {
+        for window_input in self.window_inputs.values_mut() {
+            window_input.set_scroll_settings(scroll_settings);
+        }
+        self.device_inputs.set_scroll_settings(scroll_settings);
+        self.scroll_settings = scroll_settings;
+    }
+
+    pub fn window_modifiers(&self, window_id: WindowId) -> Option<Modifiers> {
+        self.window_inputs
+            .get(&window_id)
+            .map(|window_input| window_input.modifiers())
+    }
+
+    pub fn key_presses(&self, window_id: WindowId) -> Option<&[KeyPress]> {
+        self.window_inputs
+            .get(&window_id)
+            .map(|window_input| window_input.key_presses())
+    }
+
+    pub fn text_input(&self, window_id: WindowId) -> Option<&TextInput> {
+        self.window_inputs
+            .get(&window_id)
+            .map(|window_input| window_input.text_input())
+    }
+
+    pub fn touches(&self, window_id: WindowId) -> Option<&Touches> {
+        self.window_inputs
+            .get(&window_id)
+            .map(|window_input| window_input.touches())
+    }
+
     pub fn cursor(&self, window_id: WindowId) -> Option<&Cursor> {
         match self.window_inputs.get(&window_id) {
             Some(window_input) => Some(window_input.cursor()),
@@ -42,38 +186,114 @@ impl Inputs {
         }
     }
 
-    pub fn device_mouse(&self, device_id: Option<DeviceId>) -> Option<&Mouse> {
+    pub fn device_mouse(&self, device_id: Option<InputDeviceId>) -> Option<&Mouse> {
         self.device_inputs.mouse(device_id)
     }
 }
 
 impl Inputs {
+    ///Time of the frame which is about to start. Buttons are timed by it.
+    pub(crate) fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub(crate) fn set_time(&mut self, time: f64) {
+        self.time = time;
+    }
+
     pub(crate) fn pre_update(&mut self) {
         for window_input in self.window_inputs.values_mut() {
-            window_input.pre_update();
+            window_input.pre_update(self.time);
+        }
+        self.device_inputs.pre_update(self.time);
+        if let Capture::Captured(_) = self.capture.get() {
+            self.capture.set(Capture::Idle);
         }
-        self.device_inputs.pre_update();
+    }
+
+    pub(crate) fn remove_window(&mut self, window_id: WindowId) {
+        self.window_inputs.remove(&window_id);
     }
 
     pub(crate) fn handle_window_input(&mut self, window_id: WindowId, input: WindowEvent) {
-        let device = match self.window_inputs.get_mut(&window_id) {
-            Some(window_input) => window_input.handle_input(input),
-            _ => {
-                let mut window_input = WindowInput::new();
-                let ret = window_input.handle_input(input);
-                self.window_inputs.insert(window_id, window_input);
-                ret
-            }
-        };
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record_window_input(window_id, &input);
+        }
+        self.handle_window_input_as(window_id, input, None);
+    }
+
+    pub(crate) fn handle_device_input(&mut self, device_id: DeviceId, input: DeviceEvent) {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record_device_input(device_id, &input);
+        }
+        self.handle_device_input_as(device_id.into(), input);
+    }
+
+    ///device_id replaces ids in the event.
+    pub(super) fn handle_window_input_as(
+        &mut self,
+        window_id: WindowId,
+        input: WindowEvent,
+        device_id: Option<InputDeviceId>,
+    ) {
+        match input {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => self.capture(ButtonSource::Key(KeyCode::from(key))),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button,
+                ..
+            } => self.capture(ButtonSource::Mouse(button.into())),
+            _ => {}
+        }
+
+        let device = self.window_input_mut(window_id).handle_input(input);
 
         if let Some(device) = device {
-            self.device_inputs.replace_mock(device.0, device.1);
+            self.device_inputs
+                .replace_mock(device_id.unwrap_or(device.0.into()), device.1);
         }
     }
 
-    pub(crate) fn handle_device_input(&mut self, device_id: DeviceId, input: DeviceEvent) {
+    pub(super) fn handle_device_input_as(&mut self, device_id: InputDeviceId, input: DeviceEvent) {
+        if let DeviceEvent::Button {
+            button,
+            state: ElementState::Pressed,
+        } = input
+        {
+            if self.device_inputs.mocks.contains_key(&device_id) {
+                self.capture(ButtonSource::DeviceButton(button));
+            }
+        }
         self.device_inputs.handle_input(device_id, input);
     }
+
+    ///gamepad_id is the id of the gamepad backend.
+    pub(crate) fn handle_gamepad_input(&mut self, gamepad_id: usize, input: GamepadInput) {
+        let device_id = InputDeviceId::Gamepad(gamepad_id);
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record_gamepad_input(device_id, input);
+        }
+        self.handle_gamepad_input_as(device_id, input);
+    }
+
+    pub(super) fn handle_gamepad_input_as(&mut self, device_id: InputDeviceId, input: GamepadInput) {
+        if let GamepadInput::Button {
+            button,
+            state: ElementState::Pressed,
+        } = input
+        {
+            self.capture(ButtonSource::GamepadButton(button));
+        }
+        self.device_inputs.handle_gamepad_input(device_id, input);
+    }
 }
 
 //
@@ -82,6 +302,13 @@ pub struct WindowInput {
     keyboard: Keyboard,
     cursor: Cursor,
     mouse: Mouse,
+    text_input: TextInput,
+    touches: Touches,
+    ///Touch which works as mouse when touches emulate it.
+    mouse_touch_id: Option<u64>,
+
+    modifiers: Modifiers,
+    key_presses: Vec<KeyPress>,
 }
 
 impl WindowInput {
@@ -90,6 +317,12 @@ impl WindowInput {
             keyboard: Keyboard::new(),
             cursor: Cursor::new(),
             mouse: Mouse::new(),
+            text_input: TextInput::new(),
+            touches: Touches::new(),
+            mouse_touch_id: None,
+
+            modifiers: Modifiers::NONE,
+            key_presses: Vec::new(),
         }
     }
 
@@ -104,13 +337,55 @@ impl WindowInput {
     pub fn mouse(&self) -> &Mouse {
         &self.mouse
     }
+
+    pub(super) fn cursor_mut(&mut self) -> &mut Cursor {
+        &mut self.cursor
+    }
+
+    pub fn text_input(&self) -> &TextInput {
+        &self.text_input
+    }
+
+    pub(super) fn text_input_mut(&mut self) -> &mut TextInput {
+        &mut self.text_input
+    }
+
+    pub fn touches(&self) -> &Touches {
+        &self.touches
+    }
+
+    ///From winit, and from pressed keys since winit doesn't tell ones held before focus.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers | self.keyboard.modifiers()
+    }
+
+    ///Keys pressed at this frame in order, including repeats.
+    pub fn key_presses(&self) -> &[KeyPress] {
+        &self.key_presses
+    }
 }
 
 impl WindowInput {
-    pub(crate) fn pre_update(&mut self) {
-        self.keyboard.pre_update();
+    pub(crate) fn pre_update(&mut self, time: f64) {
+        self.keyboard.pre_update(time);
         self.cursor.pre_update();
-        self.mouse.pre_update();
+        self.mouse.pre_update(time);
+        self.text_input.pre_update();
+        self.touches.pre_update(time);
+        self.key_presses.clear();
+    }
+
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.keyboard.set_click_settings(click_settings);
+        self.mouse.set_click_settings(click_settings);
+    }
+
+    pub(crate) fn set_touch_settings(&mut self, touch_settings: TouchSettings) {
+        self.touches.set_settings(touch_settings);
+    }
+
+    pub(crate) fn set_scroll_settings(&mut self, scroll_settings: ScrollSettings) {
+        self.mouse.set_scroll_settings(scroll_settings);
     }
 
     pub(crate) fn handle_input(&mut self, input: WindowEvent) -> Option<(DeviceId, DeviceType)> {
@@ -118,22 +393,113 @@ impl WindowInput {
             WindowEvent::KeyboardInput {
                 device_id, input, ..
             } => {
+                if let (ElementState::Pressed, Some(key)) = (input.state, input.virtual_keycode) {
+                    //Modifiers before this key, so pressing Control alone isn't Control+Control.
+                    let modifiers = self.modifiers();
+                    self.key_presses.push(KeyPress {
+                        key: key.into(),
+                        modifiers,
+                    });
+                    self.text_input.handle_key(key.into(), modifiers);
+                }
                 self.keyboard.handle_input(input);
                 Some((device_id, DeviceType::Keyboard))
             }
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = state.into();
+                None
+            }
+            WindowEvent::ReceivedCharacter(character) => {
+                self.text_input.handle_character(character);
+                None
+            }
+            //Releases while unfocused are told to other windows, so these would stay pressed.
+            WindowEvent::Focused(false) => {
+                self.keyboard.release_all();
+                self.mouse.release_all();
+                self.modifiers = Modifiers::NONE;
+                None
+            }
             WindowEvent::CursorMoved { .. }
             | WindowEvent::CursorEntered { .. }
             | WindowEvent::CursorLeft { .. } => self.cursor.handle_input(input),
-            WindowEvent::MouseWheel { .. } | WindowEvent::MouseInput { .. } => {
-                self.mouse.handle_window_input(input)
+            WindowEvent::MouseWheel { .. } | WindowEvent::MouseInput { .. } => self
+                .mouse
+                .handle_window_input(input, self.cursor.position()),
+            WindowEvent::Touch(touch) => {
+                self.touches.handle_input(&input);
+                if self.touches.settings().emulate_mouse {
+                    self.emulate_mouse(touch);
+                }
+                None
+            }
+            WindowEvent::TouchpadPressure { .. } => {
+                self.touches.handle_input(&input);
+                None
             }
             _ => None,
         }
     }
+
+    ///Only the first of touches at the same time works as mouse.
+    #[allow(deprecated)]
+    fn emulate_mouse(&mut self, touch: Touch) {
+        let device_id = touch.device_id;
+        let moved = WindowEvent::CursorMoved {
+            device_id,
+            position: touch.location,
+            modifiers: ModifiersState::empty(),
+        };
+        let button = |state| WindowEvent::MouseInput {
+            device_id,
+            state,
+            button: winit::event::MouseButton::Left,
+            modifiers: ModifiersState::empty(),
+        };
+
+        match (touch.phase, self.mouse_touch_id) {
+            (TouchPhase::Started, None) => {
+                self.mouse_touch_id = Some(touch.id);
+                self.cursor
+                    .handle_input(WindowEvent::CursorEntered { device_id });
+                self.cursor.handle_input(moved);
+                self.mouse
+                    .handle_window_input(button(ElementState::Pressed), self.cursor.position());
+            }
+            (TouchPhase::Moved, Some(id)) if id == touch.id => {
+                self.cursor.handle_input(moved);
+            }
+            (TouchPhase::Ended, Some(id)) | (TouchPhase::Cancelled, Some(id)) if id == touch.id => {
+                self.mouse_touch_id = None;
+                self.cursor.handle_input(moved);
+                self.mouse
+                    .handle_window_input(button(ElementState::Released), self.cursor.position());
+                self.cursor
+                    .handle_input(WindowEvent::CursorLeft { device_id });
+            }
+            _ => {}
+        }
+    }
 }
 
 //
 
+///Devices from winit or the gamepad backend,
+///or from a replayed recording or simulation which can't make their ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputDeviceId {
+    Winit(DeviceId),
+    Gamepad(usize),
+    Replayed(u32),
+    Simulated(u32),
+}
+
+impl From<DeviceId> for InputDeviceId {
+    fn from(device_id: DeviceId) -> Self {
+        InputDeviceId::Winit(device_id)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum DeviceType {
     Keyboard,
@@ -143,13 +509,22 @@ pub enum DeviceType {
 //
 
 pub struct DeviceInputs {
-    keyboards: HashMap<DeviceId, Keyboard>,
-    primary_keyboard_id: Option<DeviceId>,
+    keyboards: HashMap<InputDeviceId, Keyboard>,
+    primary_keyboard_id: Option<InputDeviceId>,
+
+    mouses: HashMap<InputDeviceId, Mouse>,
+    primary_mouse_id: Option<InputDeviceId>,
+
+    gamepads: HashMap<InputDeviceId, Gamepad>,
+    primary_gamepad_id: Option<InputDeviceId>,
+    gamepad_settings: GamepadSettings,
+    gamepad_events: Vec<GamepadEvent>,
 
-    mouses: HashMap<DeviceId, Mouse>,
-    primary_mouse_id: Option<DeviceId>,
+    mocks: HashMap<InputDeviceId, MockDevice>,
 
-    mocks: HashMap<DeviceId, MockDevice>,
+    time: f64,
+    click_settings: ClickSettings,
+    scroll_settings: ScrollSettings,
 }
 
 impl DeviceInputs {
@@ -161,11 +536,20 @@ impl DeviceInputs {
             mouses: HashMap::new(),
             primary_mouse_id: None,
 
+            gamepads: HashMap::new(),
+            primary_gamepad_id: None,
+            gamepad_settings: GamepadSettings::default(),
+            gamepad_events: Vec::new(),
+
             mocks: HashMap::new(),
+
+            time: 0.0,
+            click_settings: ClickSettings::default(),
+            scroll_settings: ScrollSettings::default(),
         }
     }
 
-    pub fn keyboard(&self, device_id: Option<DeviceId>) -> Option<&Keyboard> {
+    pub fn keyboard(&self, device_id: Option<InputDeviceId>) -> Option<&Keyboard> {
         match device_id {
             Some(device_id) => self.keyboards.get(&device_id),
             _ => match self.primary_keyboard_id {
@@ -175,7 +559,7 @@ impl DeviceInputs {
         }
     }
 
-    pub fn mouse(&self, device_id: Option<DeviceId>) -> Option<&Mouse> {
+    pub fn mouse(&self, device_id: Option<InputDeviceId>) -> Option<&Mouse> {
         match device_id {
             Some(device_id) => self.mouses.get(&device_id),
             _ => match self.primary_mouse_id {
@@ -184,37 +568,124 @@ impl DeviceInputs {
             },
         }
     }
+
+    pub fn gamepad(&self, device_id: Option<InputDeviceId>) -> Option<&Gamepad> {
+        match device_id {
+            Some(device_id) => self.gamepads.get(&device_id),
+            _ => match self.primary_gamepad_id {
+                Some(primary_gamepad_id) => self.gamepads.get(&primary_gamepad_id),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn gamepads(&self) -> impl Iterator<Item = (&InputDeviceId, &Gamepad)> {
+        self.gamepads.iter()
+    }
+
+    ///Connections and disconnections at this frame.
+    pub fn gamepad_events(&self) -> &[GamepadEvent] {
+        &self.gamepad_events
+    }
+
+    ///Applied to connected gamepads and later ones.
+    pub fn set_gamepad_settings(&mut self, settings: GamepadSettings) {
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.set_settings(settings);
+        }
+        self.gamepad_settings = settings;
+    }
+
+    pub fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        for keyboard in self.keyboards.values_mut() {
+            keyboard.set_click_settings(click_settings);
+        }
+        for mouse in self.mouses.values_mut() {
+            mouse.set_click_settings(click_settings);
+        }
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.set_click_settings(click_settings);
+        }
+        for mock in self.mocks.values_mut() {
+            mock.set_click_settings(click_settings);
+        }
+        self.click_settings = click_settings;
+    }
+
+    ///Applied to known mice and later ones.
+    pub fn set_scroll_settings(&mut self, scroll_settings: ScrollSettings) {
+        for mouse in self.mouses.values_mut() {
+            mouse.set_scroll_settings(scroll_settings);
+        }
+        self.scroll_settings = scroll_settings;
+    }
+
+    ///Devices which are not classified yet.
+    pub fn mocks(&self) -> impl Iterator<Item = &MockDevice> {
+        self.mocks.values()
+    }
 }
 
 impl DeviceInputs {
-    fn remove_device(&mut self, device_id: DeviceId) {
+    fn is_known(&self, device_id: InputDeviceId) -> bool {
+        self.mocks.contains_key(&device_id)
+            || self.keyboards.contains_key(&device_id)
+            || self.mouses.contains_key(&device_id)
+            || self.gamepads.contains_key(&device_id)
+    }
+
+    fn new_mock(&self) -> MockDevice {
+        let mut mock = MockDevice::new();
+        mock.pre_update(self.time);
+        mock.set_click_settings(self.click_settings);
+        mock
+    }
+
+    fn remove_device(&mut self, device_id: InputDeviceId) {
         if let Some(_) = self.mocks.remove(&device_id) {
         } else if let Some(_) = self.keyboards.remove(&device_id) {
             self.primary_keyboard_id = self.keyboards.keys().next().copied();
         } else if let Some(_) = self.mouses.remove(&device_id) {
             self.primary_mouse_id = self.mouses.keys().next().copied();
+        } else if let Some(_) = self.gamepads.remove(&device_id) {
+            if self.primary_gamepad_id == Some(device_id) {
+                self.primary_gamepad_id = self.gamepads.keys().next().copied();
+            }
+            self.gamepad_events
+                .push(GamepadEvent::Disconnected(device_id));
         }
     }
 
-    pub(crate) fn pre_update(&mut self) {
+    pub(crate) fn pre_update(&mut self, time: f64) {
+        self.time = time;
+        self.gamepad_events.clear();
+
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.pre_update(time);
+        }
         for mock in self.mocks.values_mut() {
-            mock.pre_update();
+            mock.pre_update(time);
         }
         for keyboard in self.keyboards.values_mut() {
-            keyboard.pre_update();
+            keyboard.pre_update(time);
         }
         for mouse in self.mouses.values_mut() {
-            mouse.pre_update();
+            mouse.pre_update(time);
         }
     }
 
-    pub(crate) fn handle_input(&mut self, device_id: DeviceId, input: DeviceEvent) {
+    pub(crate) fn handle_input(&mut self, device_id: InputDeviceId, input: DeviceEvent) {
         match input {
             DeviceEvent::Added => {
-                self.mocks.insert(device_id, MockDevice::new());
+                self.mocks.insert(device_id, self.new_mock());
             }
             DeviceEvent::Removed => self.remove_device(device_id),
             _ => {
+                //Not every platform tells devices added at start.
+                if !self.is_known(device_id) {
+                    self.mocks.insert(device_id, self.new_mock());
+                }
+
                 if let Some(mock) = self.mocks.get_mut(&device_id) {
                     if let Some(device_type) = mock.handle_input(&input) {
                         self.replace_mock(device_id, device_type);
@@ -222,8 +693,10 @@ impl DeviceInputs {
                 }
 
                 if let Some(keyboard) = self.keyboards.get_mut(&device_id) {
-                    if let DeviceEvent::Key(input) = input {
-                        keyboard.handle_input(input);
+                    match input {
+                        DeviceEvent::Key(input) => keyboard.handle_input(input),
+                        DeviceEvent::Text { codepoint } => keyboard.handle_text(codepoint),
+                        _ => {}
                     }
                 } else if let Some(mouse) = self.mouses.get_mut(&device_id) {
                     mouse.handle_device_input(input);
@@ -232,7 +705,29 @@ impl DeviceInputs {
         }
     }
 
-    pub(crate) fn replace_mock(&mut self, device_id: DeviceId, device_type: DeviceType) {
+    pub(crate) fn handle_gamepad_input(&mut self, device_id: InputDeviceId, input: GamepadInput) {
+        match input {
+            GamepadInput::Disconnected => self.remove_device(device_id),
+            _ => {
+                //A replay may start with gamepads already connected.
+                if !self.gamepads.contains_key(&device_id) {
+                    let mut gamepad = Gamepad::new(self.gamepad_settings);
+                    gamepad.pre_update(self.time);
+                    gamepad.set_click_settings(self.click_settings);
+                    self.gamepads.insert(device_id, gamepad);
+                    if let None = self.primary_gamepad_id {
+                        self.primary_gamepad_id = Some(device_id);
+                    }
+                    self.gamepad_events.push(GamepadEvent::Connected(device_id));
+                }
+                if let Some(gamepad) = self.gamepads.get_mut(&device_id) {
+                    gamepad.handle_input(input);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn replace_mock(&mut self, device_id: InputDeviceId, device_type: DeviceType) {
         if let Some(mock) = self.mocks.remove(&device_id) {
             match device_type {
                 DeviceType::Keyboard => {
@@ -245,7 +740,9 @@ impl DeviceInputs {
                     if let None = self.primary_mouse_id {
                         self.primary_mouse_id = Some(device_id);
                     }
-                    self.mouses.insert(device_id, mock.into());
+                    let mut mouse: Mouse = mock.into();
+                    mouse.set_scroll_settings(self.scroll_settings);
+                    self.mouses.insert(device_id, mouse);
                 }
             }
         }
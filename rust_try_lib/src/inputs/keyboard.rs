@@ -1,7 +1,9 @@
 use super::{buttons::*, mock::*};
 
+use winit::event::VirtualKeyCode;
+
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum KeyCode {
     Key1,
     Key2,
@@ -170,6 +172,218 @@ pub enum KeyCode {
 
 impl KeyCode {
     const LEN: usize = 163;
+
+    ///Modifier of the key. Left and right keys give same one.
+    pub fn modifier(self) -> Option<Modifiers> {
+        match self {
+            KeyCode::LShift | KeyCode::RShift => Some(Modifiers::SHIFT),
+            KeyCode::LControl | KeyCode::RControl => Some(Modifiers::CONTROL),
+            KeyCode::LAlt | KeyCode::RAlt => Some(Modifiers::ALT),
+            KeyCode::LWin | KeyCode::RWin => Some(Modifiers::LOGO),
+            _ => None,
+        }
+    }
+
+    ///Scancode at the position of the key on a US layout. None out of the main typing area.
+    pub fn scancode(self) -> Option<u32> {
+        SCANCODES
+            .iter()
+            .find(|(key, _)| *key == self)
+            .map(|(_, scancode)| *scancode)
+    }
+
+    ///Key at the position of scancode on a US layout.
+    pub fn from_scancode(scancode: u32) -> Option<KeyCode> {
+        SCANCODES
+            .iter()
+            .find(|(_, code)| *code == scancode)
+            .map(|(key, _)| *key)
+    }
+}
+
+///Given by simulation for keys without a scancode. No key position is pressed by it.
+pub(crate) const NO_SCANCODE: u32 = u32::MAX;
+
+///Scancodes of the main typing area on a US layout. Windows and Linux share them there.
+#[cfg(not(target_os = "macos"))]
+const SCANCODES: [(KeyCode, u32); 56] = [
+    (KeyCode::Escape, 1),
+    (KeyCode::Key1, 2),
+    (KeyCode::Key2, 3),
+    (KeyCode::Key3, 4),
+    (KeyCode::Key4, 5),
+    (KeyCode::Key5, 6),
+    (KeyCode::Key6, 7),
+    (KeyCode::Key7, 8),
+    (KeyCode::Key8, 9),
+    (KeyCode::Key9, 10),
+    (KeyCode::Key0, 11),
+    (KeyCode::Minus, 12),
+    (KeyCode::Equals, 13),
+    (KeyCode::Back, 14),
+    (KeyCode::Tab, 15),
+    (KeyCode::Q, 16),
+    (KeyCode::W, 17),
+    (KeyCode::E, 18),
+    (KeyCode::R, 19),
+    (KeyCode::T, 20),
+    (KeyCode::Y, 21),
+    (KeyCode::U, 22),
+    (KeyCode::I, 23),
+    (KeyCode::O, 24),
+    (KeyCode::P, 25),
+    (KeyCode::LBracket, 26),
+    (KeyCode::RBracket, 27),
+    (KeyCode::Return, 28),
+    (KeyCode::LControl, 29),
+    (KeyCode::A, 30),
+    (KeyCode::S, 31),
+    (KeyCode::D, 32),
+    (KeyCode::F, 33),
+    (KeyCode::G, 34),
+    (KeyCode::H, 35),
+    (KeyCode::J, 36),
+    (KeyCode::K, 37),
+    (KeyCode::L, 38),
+    (KeyCode::Semicolon, 39),
+    (KeyCode::Apostrophe, 40),
+    (KeyCode::Grave, 41),
+    (KeyCode::LShift, 42),
+    (KeyCode::Backslash, 43),
+    (KeyCode::Z, 44),
+    (KeyCode::X, 45),
+    (KeyCode::C, 46),
+    (KeyCode::V, 47),
+    (KeyCode::B, 48),
+    (KeyCode::N, 49),
+    (KeyCode::M, 50),
+    (KeyCode::Comma, 51),
+    (KeyCode::Period, 52),
+    (KeyCode::Slash, 53),
+    (KeyCode::RShift, 54),
+    (KeyCode::LAlt, 56),
+    (KeyCode::Space, 57),
+];
+
+#[cfg(target_os = "macos")]
+const SCANCODES: [(KeyCode, u32); 57] = [
+    (KeyCode::A, 0),
+    (KeyCode::S, 1),
+    (KeyCode::D, 2),
+    (KeyCode::F, 3),
+    (KeyCode::H, 4),
+    (KeyCode::G, 5),
+    (KeyCode::Z, 6),
+    (KeyCode::X, 7),
+    (KeyCode::C, 8),
+    (KeyCode::V, 9),
+    (KeyCode::B, 11),
+    (KeyCode::Q, 12),
+    (KeyCode::W, 13),
+    (KeyCode::E, 14),
+    (KeyCode::R, 15),
+    (KeyCode::Y, 16),
+    (KeyCode::T, 17),
+    (KeyCode::Key1, 18),
+    (KeyCode::Key2, 19),
+    (KeyCode::Key3, 20),
+    (KeyCode::Key4, 21),
+    (KeyCode::Key6, 22),
+    (KeyCode::Key5, 23),
+    (KeyCode::Equals, 24),
+    (KeyCode::Key9, 25),
+    (KeyCode::Key7, 26),
+    (KeyCode::Minus, 27),
+    (KeyCode::Key8, 28),
+    (KeyCode::Key0, 29),
+    (KeyCode::RBracket, 30),
+    (KeyCode::O, 31),
+    (KeyCode::U, 32),
+    (KeyCode::LBracket, 33),
+    (KeyCode::I, 34),
+    (KeyCode::P, 35),
+    (KeyCode::Return, 36),
+    (KeyCode::L, 37),
+    (KeyCode::J, 38),
+    (KeyCode::Apostrophe, 39),
+    (KeyCode::K, 40),
+    (KeyCode::Semicolon, 41),
+    (KeyCode::Backslash, 42),
+    (KeyCode::Comma, 43),
+    (KeyCode::Slash, 44),
+    (KeyCode::N, 45),
+    (KeyCode::M, 46),
+    (KeyCode::Period, 47),
+    (KeyCode::Tab, 48),
+    (KeyCode::Space, 49),
+    (KeyCode::Grave, 50),
+    (KeyCode::Back, 51),
+    (KeyCode::Escape, 53),
+    (KeyCode::LWin, 55),
+    (KeyCode::LShift, 56),
+    (KeyCode::LAlt, 58),
+    (KeyCode::LControl, 59),
+    (KeyCode::RShift, 60),
+];
+
+///Defines KeyCode::ALL and conversions with winit by names of keys, not by their order.
+macro_rules! winit_keys {
+    ($($key:ident),* $(,)?) => {
+        impl KeyCode {
+            pub const ALL: [KeyCode; KeyCode::LEN] = [$(KeyCode::$key),*];
+        }
+
+        impl From<VirtualKeyCode> for KeyCode {
+            fn from(key: VirtualKeyCode) -> Self {
+                match key {
+                    $(VirtualKeyCode::$key => KeyCode::$key,)*
+                }
+            }
+        }
+
+        impl From<KeyCode> for VirtualKeyCode {
+            fn from(key: KeyCode) -> Self {
+                match key {
+                    $(KeyCode::$key => VirtualKeyCode::$key,)*
+                }
+            }
+        }
+    };
+}
+
+winit_keys! {
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, A, B, C, D, E, F, G, H, I, J, K,
+    L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9,
+    F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, Snapshot, Scroll,
+    Pause, Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right, Down, Back, Return,
+    Space, Compose, Caret, Numlock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5,
+    Numpad6, Numpad7, Numpad8, Numpad9, NumpadAdd, NumpadDivide, NumpadDecimal, NumpadComma,
+    NumpadEnter, NumpadEquals, NumpadMultiply, NumpadSubtract, AbntC1, AbntC2, Apostrophe, Apps,
+    Asterisk, At, Ax, Backslash, Calculator, Capital, Colon, Comma, Convert, Equals, Grave,
+    Kana, Kanji, LAlt, LBracket, LControl, LShift, LWin, Mail, MediaSelect, MediaStop, Minus,
+    Mute, MyComputer, NavigateForward, NavigateBackward, NextTrack, NoConvert, OEM102, Period,
+    PlayPause, Plus, Power, PrevTrack, RAlt, RBracket, RControl, RShift, RWin, Semicolon, Slash,
+    Sleep, Stop, Sysrq, Tab, Underline, Unlabeled, VolumeDown, VolumeUp, Wake, WebBack,
+    WebFavorites, WebForward, WebHome, WebRefresh, WebSearch, WebStop, Yen, Copy, Paste, Cut,
+}
+
+impl std::fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+///Parses the variant name, e.g. "Space".
+impl std::str::FromStr for KeyCode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyCode::ALL
+            .iter()
+            .copied()
+            .find(|key| format!("{:?}", key) == s)
+            .ok_or(())
+    }
 }
 
 impl From<KeyCode> for usize {
@@ -178,27 +392,207 @@ impl From<KeyCode> for usize {
     }
 }
 
+///Modifier keys. Left and right keys are treated as same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const SHIFT: Modifiers = Modifiers(1);
+    pub const CONTROL: Modifiers = Modifiers(1 << 1);
+    pub const ALT: Modifiers = Modifiers(1 << 2);
+    pub const LOGO: Modifiers = Modifiers(1 << 3);
+
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn remove(&mut self, other: Modifiers) {
+        self.0 &= !other.0;
+    }
+}
+
+impl From<winit::event::ModifiersState> for Modifiers {
+    fn from(state: winit::event::ModifiersState) -> Self {
+        let mut modifiers = Modifiers::NONE;
+        for (pressed, modifier) in [
+            (state.shift(), Modifiers::SHIFT),
+            (state.ctrl(), Modifiers::CONTROL),
+            (state.alt(), Modifiers::ALT),
+            (state.logo(), Modifiers::LOGO),
+        ] {
+            if pressed {
+                modifiers |= modifier;
+            }
+        }
+        modifiers
+    }
+}
+
+impl From<Modifiers> for winit::event::ModifiersState {
+    fn from(modifiers: Modifiers) -> Self {
+        use winit::event::ModifiersState;
+
+        let mut state = ModifiersState::empty();
+        for (modifier, flag) in [
+            (Modifiers::SHIFT, ModifiersState::SHIFT),
+            (Modifiers::CONTROL, ModifiersState::CTRL),
+            (Modifiers::ALT, ModifiersState::ALT),
+            (Modifiers::LOGO, ModifiersState::LOGO),
+        ] {
+            if modifiers.contains(modifier) {
+                state |= flag;
+            }
+        }
+        state
+    }
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.0 |= other.0;
+    }
+}
+
+const MODIFIER_NAMES: [(&str, Modifiers); 4] = [
+    ("Shift", Modifiers::SHIFT),
+    ("Control", Modifiers::CONTROL),
+    ("Alt", Modifiers::ALT),
+    ("Logo", Modifiers::LOGO),
+];
+
+impl std::fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names: Vec<&str> = MODIFIER_NAMES
+            .iter()
+            .filter(|(_, modifier)| self.contains(*modifier))
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join("+"))
+    }
+}
+
+///e.g. "Control+Shift". Empty string is none.
+impl std::str::FromStr for Modifiers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::NONE;
+        for name in s.split('+').map(str::trim).filter(|name| !name.is_empty()) {
+            match MODIFIER_NAMES.iter().find(|(known, _)| *known == name) {
+                Some((_, modifier)) => modifiers |= *modifier,
+                None => return Err(format!("unknown modifier `{}`", name)),
+            }
+        }
+        Ok(modifiers)
+    }
+}
+
 pub struct Keyboard {
     buttons: Buttons,
+    scancodes: Buttons,
+    texts: String,
 }
 
 impl Keyboard {
     pub fn new() -> Self {
         Self {
             buttons: Buttons::new(KeyCode::LEN),
+            scancodes: Buttons::new(128),
+            texts: String::new(),
         }
     }
+
+    ///Keys by position regardless of layout. Also has keys without KeyCode.
+    pub fn scancodes(&self) -> &Buttons {
+        &self.scancodes
+    }
+
+    ///Whether the key at the position of given key on a US layout is pressed.
+    ///e.g. W is at the position of Z on AZERTY.
+    pub fn is_physical_pressed(&self, key: KeyCode) -> bool {
+        match key.scancode() {
+            Some(scancode) => self.scancodes.is_pressed(scancode as usize),
+            None => false,
+        }
+    }
+
+    pub fn was_physical_pressed(&self, key: KeyCode) -> bool {
+        match key.scancode() {
+            Some(scancode) => self.scancodes.was_pressed(scancode as usize),
+            None => false,
+        }
+    }
+
+    pub fn is_physical_just_pressed(&self, key: KeyCode) -> bool {
+        match key.scancode() {
+            Some(scancode) => self.scancodes.is_just_pressed(scancode as usize),
+            None => false,
+        }
+    }
+
+    ///Text events from the device at this frame. Window text input is in TextInput.
+    pub fn texts(&self) -> &str {
+        &self.texts
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let mut modifiers = Modifiers::NONE;
+        for (keys, modifier) in [
+            ([KeyCode::LShift, KeyCode::RShift], Modifiers::SHIFT),
+            ([KeyCode::LControl, KeyCode::RControl], Modifiers::CONTROL),
+            ([KeyCode::LAlt, KeyCode::RAlt], Modifiers::ALT),
+            ([KeyCode::LWin, KeyCode::RWin], Modifiers::LOGO),
+        ] {
+            if keys.iter().any(|key| self.is_pressed(*key)) {
+                modifiers |= modifier;
+            }
+        }
+        modifiers
+    }
 }
 
 impl Keyboard {
-    pub(crate) fn pre_update(&mut self) {
-        self.buttons.pre_update();
+    pub(crate) fn pre_update(&mut self, time: f64) {
+        self.buttons.pre_update(time);
+        self.scancodes.pre_update(time);
+        self.texts.clear();
+    }
+
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.buttons.set_click_settings(click_settings);
+        self.scancodes.set_click_settings(click_settings);
+    }
+
+    pub(crate) fn release_all(&mut self) {
+        self.buttons.release_all();
+        self.scancodes.release_all();
+    }
+
+    pub(crate) fn handle_text(&mut self, codepoint: char) {
+        self.texts.push(codepoint);
     }
 
     pub(crate) fn handle_input(&mut self, keyboard_input: winit::event::KeyboardInput) {
+        if keyboard_input.scancode != NO_SCANCODE {
+            self.scancodes
+                .handle_input(keyboard_input.scancode as usize, keyboard_input.state);
+        }
         if let Some(key) = keyboard_input.virtual_keycode {
             self.buttons
-                .handle_input(key as usize, keyboard_input.state);
+                .handle_input(KeyCode::from(key), keyboard_input.state);
         }
     }
 }
@@ -212,7 +606,70 @@ impl std::ops::Deref for Keyboard {
 }
 
 impl From<MockDevice> for Keyboard {
-    fn from(_: MockDevice) -> Self {
-        Self::new()
+    fn from(mock: MockDevice) -> Self {
+        let mut keyboard = Self::new();
+        keyboard.pre_update(mock.buttons.time());
+        keyboard.set_click_settings(mock.buttons.click_settings());
+        keyboard.texts = mock.texts;
+        keyboard
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::inputs::{ActionBinding, ButtonSource, Inputs};
+    use winit::window::WindowId;
+
+    #[test]
+    fn winit_round_trip() {
+        for key in KeyCode::ALL {
+            let virtual_key = VirtualKeyCode::from(key);
+            assert_eq!(format!("{:?}", virtual_key), format!("{:?}", key));
+            assert_eq!(KeyCode::from(virtual_key), key);
+        }
+        for (key, _) in SCANCODES {
+            assert_eq!(KeyCode::from_scancode(key.scancode().unwrap()), Some(key));
+        }
+    }
+
+    #[test]
+    fn physical_key_on_azerty() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.input_map_mut().bind_action(
+            "forward",
+            ActionBinding::new(ButtonSource::PhysicalKey(KeyCode::W)),
+        );
+
+        //Z is where W is on a US layout.
+        let scancode = KeyCode::W.scancode().unwrap();
+        inputs.simulate_key_at(window_id, scancode, Some(KeyCode::Z), ButtonState::Pressed);
+        let keyboard = inputs.window_keyboard(window_id).unwrap();
+        assert!(keyboard.is_pressed(KeyCode::Z));
+        assert!(!keyboard.is_pressed(KeyCode::W));
+        assert!(keyboard.is_physical_just_pressed(KeyCode::W));
+        assert!(inputs.action("forward").is_just_pressed());
+
+        inputs.simulate_key_at(window_id, 0x1b8, None, ButtonState::Pressed);
+        assert!(inputs
+            .window_keyboard(window_id)
+            .unwrap()
+            .scancodes()
+            .is_pressed(0x1b8usize));
+    }
+
+    #[test]
+    fn unmapped_key_presses_no_scancode() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        assert_eq!(KeyCode::F1.scancode(), None);
+
+        inputs.simulate_key(window_id, KeyCode::F1, ButtonState::Pressed);
+        let keyboard = inputs.window_keyboard(window_id).unwrap();
+        assert!(keyboard.is_pressed(KeyCode::F1));
+        assert!(!keyboard.is_physical_pressed(KeyCode::A));
+        assert!(!keyboard.scancodes().is_pressed(0usize));
     }
 }
@@ -2,7 +2,8 @@ use super::{buttons::*, inputs::DeviceType};
 
 use winit::event::*;
 
-///Replacement of unfamiliar devices. Could be deleted someday.
+///Device whose type is not known yet. Becomes keyboard or mouse by its events.
+///Gamepads come from the gamepad backend instead, so other devices stay here.
 pub struct MockDevice {
     pub(super) first_motion: Vec<Option<f32>>,
     pub(super) motion: Vec<f32>,
@@ -40,12 +41,16 @@ impl MockDevice {
 }
 
 impl MockDevice {
-    pub(crate) fn pre_update(&mut self) {
+    pub(crate) fn pre_update(&mut self, time: f64) {
         self.first_motion.fill(None);
         self.motion.fill(0.0);
         self.last_motion.fill(0.0);
         self.texts.clear();
-        self.buttons.pre_update();
+        self.buttons.pre_update(time);
+    }
+
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.buttons.set_click_settings(click_settings);
     }
 
     pub(crate) fn handle_input(&mut self, input: &DeviceEvent) -> Option<DeviceType> {
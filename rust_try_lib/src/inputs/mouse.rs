@@ -4,7 +4,7 @@ use cgmath::*;
 
 use winit::event::*;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -35,11 +35,44 @@ impl From<WinitMouseButton> for MouseButton {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ScrollUnit {
+    ///Notches of a wheel.
+    Lines,
+    ///Precise deltas of trackpads and smooth wheels.
+    Pixels,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollSettings {
+    ///Pixels per line, to convert one to another.
+    pub line_height: f32,
+    ///Seconds for smooth scroll to cover most of the distance. 0 scrolls at once.
+    pub smoothing: f64,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            line_height: 20.0,
+            smoothing: 0.1,
+        }
+    }
+}
+
 pub struct Mouse {
     first_motion: Option<Vector2<f32>>,
     motion: Vector2<f32>,
     last_motion: Vector2<f32>,
-    wheel: f32,
+
+    ///In pixels, with lines converted.
+    wheel: Vector2<f32>,
+    wheel_unit: Option<ScrollUnit>,
+    ///Pixels not scrolled smoothly yet, including ones at this frame.
+    smooth_remaining: Vector2<f32>,
+    ///Part of smooth_remaining scrolled at this frame.
+    smooth_rate: f32,
+    scroll_settings: ScrollSettings,
 
     buttons: Buttons,
 }
@@ -50,7 +83,12 @@ impl Mouse {
             first_motion: None,
             motion: Vector2::zero(),
             last_motion: Vector2::zero(),
-            wheel: 0.0,
+
+            wheel: Vector2::zero(),
+            wheel_unit: None,
+            smooth_remaining: Vector2::zero(),
+            smooth_rate: 1.0,
+            scroll_settings: ScrollSettings::default(),
 
             buttons: Buttons::new(3),
         }
@@ -68,33 +106,96 @@ impl Mouse {
         self.last_motion
     }
 
+    ///Vertical lines at this frame, up is positive.
     pub fn wheel(&self) -> f32 {
+        self.wheel_lines().y
+    }
+
+    ///Scrolled lines at this frame. Right and up are positive.
+    pub fn wheel_lines(&self) -> Vector2<f32> {
+        self.wheel / self.scroll_settings.line_height
+    }
+
+    pub fn wheel_pixels(&self) -> Vector2<f32> {
         self.wheel
     }
+
+    ///Unit of the last scroll at this frame. None when not scrolled.
+    pub fn wheel_unit(&self) -> Option<ScrollUnit> {
+        self.wheel_unit
+    }
+
+    ///Pixels to scroll at this frame, spreading each scroll over a few frames.
+    pub fn smooth_wheel(&self) -> Vector2<f32> {
+        self.smooth_remaining * self.smooth_rate
+    }
+
+    pub fn scroll_settings(&self) -> ScrollSettings {
+        self.scroll_settings
+    }
 }
 
 impl Mouse {
-    pub(crate) fn pre_update(&mut self) {
+    pub(crate) fn pre_update(&mut self, time: f64) {
         self.first_motion = None;
         self.motion.set_zero();
         self.last_motion.set_zero();
-        self.wheel = 0.0;
 
-        self.buttons.pre_update();
+        self.wheel.set_zero();
+        self.wheel_unit = None;
+        self.smooth_remaining -= self.smooth_wheel();
+        if self.smooth_remaining.magnitude2() < 1e-4 {
+            self.smooth_remaining.set_zero();
+        }
+        let smoothing = self.scroll_settings.smoothing;
+        self.smooth_rate = if smoothing > 0.0 {
+            let delta = (time - self.buttons.time()).max(0.0);
+            (1.0 - (-delta / smoothing).exp()) as f32
+        } else {
+            1.0
+        };
+
+        self.buttons.pre_update(time);
     }
 
+    pub(crate) fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.buttons.set_click_settings(click_settings);
+    }
+
+    pub(crate) fn release_all(&mut self) {
+        self.buttons.release_all();
+    }
+
+    pub(crate) fn set_scroll_settings(&mut self, scroll_settings: ScrollSettings) {
+        self.scroll_settings = scroll_settings;
+    }
+
+    fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        let (pixels, unit) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (
+                vec2(x, y) * self.scroll_settings.line_height,
+                ScrollUnit::Lines,
+            ),
+            MouseScrollDelta::PixelDelta(pixels) => {
+                (vec2(pixels.x as f32, pixels.y as f32), ScrollUnit::Pixels)
+            }
+        };
+        self.wheel += pixels;
+        self.wheel_unit = Some(unit);
+        self.smooth_remaining += pixels;
+    }
+
+    ///position is of cursor in the window.
     pub(crate) fn handle_window_input(
         &mut self,
         input: WindowEvent,
+        position: Point2<f32>,
     ) -> Option<(DeviceId, DeviceType)> {
         match input {
             WindowEvent::MouseWheel {
                 device_id, delta, ..
             } => {
-                match delta {
-                    MouseScrollDelta::LineDelta(_, y) => self.wheel += y,
-                    MouseScrollDelta::PixelDelta(pixels) => self.wheel += pixels.y as f32,
-                }
+                self.handle_scroll(delta);
                 Some((device_id, DeviceType::Mouse))
             }
             WindowEvent::MouseInput {
@@ -103,8 +204,11 @@ impl Mouse {
                 button,
                 ..
             } => {
-                self.buttons
-                    .handle_input(Into::<MouseButton>::into(button), state);
+                self.buttons.handle_input_at(
+                    Into::<MouseButton>::into(button),
+                    state,
+                    Some(position),
+                );
                 Some((device_id, DeviceType::Mouse))
             }
             _ => None,
@@ -121,10 +225,7 @@ impl Mouse {
                 self.motion += delta;
                 self.last_motion = delta;
             }
-            DeviceEvent::MouseWheel { delta } => match delta {
-                MouseScrollDelta::LineDelta(_, y) => self.wheel += y,
-                MouseScrollDelta::PixelDelta(pixels) => self.wheel += pixels.y as f32,
-            },
+            DeviceEvent::MouseWheel { delta } => self.handle_scroll(delta),
             DeviceEvent::Button { button, state } => {
                 self.buttons.handle_input(button as usize, state)
             }
@@ -158,9 +259,82 @@ impl From<MockDevice> for Mouse {
             },
             motion: vec2(mock.motion[0], mock.motion[1]),
             last_motion: vec2(mock.last_motion[0], mock.last_motion[1]),
-            wheel: 0.0,
+
+            wheel: Vector2::zero(),
+            wheel_unit: None,
+            smooth_remaining: Vector2::zero(),
+            smooth_rate: 1.0,
+            scroll_settings: ScrollSettings::default(),
 
             buttons: mock.buttons,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::inputs::Inputs;
+    use winit::window::WindowId;
+
+    #[test]
+    fn double_click_in_place() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        let mut click = |inputs: &mut Inputs, delta: f64, position: Point2<f32>| {
+            inputs.advance_frame_by(delta);
+            inputs.simulate_cursor_move(window_id, position);
+            inputs.simulate_mouse_button(window_id, MouseButton::Left, ButtonState::Pressed);
+            inputs.simulate_mouse_button(window_id, MouseButton::Left, ButtonState::Released);
+            inputs
+                .window_mouse(window_id)
+                .unwrap()
+                .click_count(MouseButton::Left)
+        };
+
+        assert_eq!(click(&mut inputs, 0.0, point2(10.0, 10.0)), 1);
+        assert_eq!(click(&mut inputs, 0.2, point2(11.0, 10.0)), 2);
+        assert_eq!(click(&mut inputs, 0.2, point2(10.0, 11.0)), 3);
+        //Too late.
+        assert_eq!(click(&mut inputs, 1.0, point2(10.0, 10.0)), 1);
+        //Too far.
+        assert_eq!(click(&mut inputs, 0.2, point2(50.0, 10.0)), 1);
+
+        inputs.set_click_settings(ClickSettings {
+            interval: 2.0,
+            slop: 100.0,
+        });
+        assert_eq!(click(&mut inputs, 1.0, point2(10.0, 10.0)), 2);
+    }
+
+    #[test]
+    fn wheel_units_and_smooth_scroll() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.set_scroll_settings(ScrollSettings {
+            line_height: 10.0,
+            smoothing: 0.1,
+        });
+
+        inputs.simulate_mouse_wheel(window_id, 2.0);
+        inputs.simulate_mouse_scroll(
+            window_id,
+            MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition::new(5.0, 0.0)),
+        );
+        let mouse = inputs.window_mouse(window_id).unwrap();
+        assert_eq!(mouse.wheel_pixels(), vec2(5.0, 20.0));
+        assert_eq!(mouse.wheel_lines(), vec2(0.5, 2.0));
+        assert_eq!(mouse.wheel_unit(), Some(ScrollUnit::Pixels));
+
+        let mut scrolled = Vector2::zero();
+        for _ in 0..60 {
+            inputs.advance_frame_by(1.0 / 60.0);
+            let mouse = inputs.window_mouse(window_id).unwrap();
+            assert!(mouse.smooth_wheel().y < 20.0);
+            scrolled += mouse.smooth_wheel();
+        }
+        assert!((scrolled - vec2(5.0, 20.0)).magnitude() < 0.01);
+        assert_eq!(inputs.window_mouse(window_id).unwrap().wheel(), 0.0);
+    }
+}
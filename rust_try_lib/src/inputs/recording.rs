@@ -0,0 +1,1145 @@
+//!Records handled events per frame and replays them.
+//!
+//!Saved as lines. A frame starts with `frame <delta>`, followed by its events.
+//!Windows and devices are saved as indexes of their first appearance.
+use super::*;
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use winit::{dpi::PhysicalPosition, event::*, window::WindowId};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordedWindowEvent {
+    KeyboardInput {
+        device: u32,
+        scancode: u32,
+        state: ElementState,
+        key: Option<KeyCode>,
+    },
+    MouseInput {
+        device: u32,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    },
+    MouseWheel {
+        device: u32,
+        delta: MouseScrollDelta,
+    },
+    CursorMoved {
+        device: u32,
+        position: (f64, f64),
+    },
+    CursorEntered {
+        device: u32,
+    },
+    CursorLeft {
+        device: u32,
+    },
+    ReceivedCharacter {
+        character: char,
+    },
+    ModifiersChanged {
+        modifiers: Modifiers,
+    },
+    ///Losing focus releases what's held in the window.
+    Focused {
+        focused: bool,
+    },
+    Touch {
+        device: u32,
+        id: u64,
+        phase: TouchPhase,
+        position: (f64, f64),
+        ///Normalized, since touches only keep that.
+        force: Option<f64>,
+    },
+    TouchpadPressure {
+        device: u32,
+        pressure: f32,
+        stage: i64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordedDeviceEvent {
+    Added,
+    Removed,
+    MouseMotion {
+        delta: (f64, f64),
+    },
+    MouseWheel {
+        delta: MouseScrollDelta,
+    },
+    Motion {
+        axis: u32,
+        value: f64,
+    },
+    Button {
+        button: u32,
+        state: ElementState,
+    },
+    Key {
+        scancode: u32,
+        state: ElementState,
+        key: Option<KeyCode>,
+    },
+    Text {
+        codepoint: char,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordedEvent {
+    Window {
+        window: u32,
+        event: RecordedWindowEvent,
+    },
+    Device {
+        device: u32,
+        event: RecordedDeviceEvent,
+    },
+    ///Devices of gamepads share indexes with the others.
+    Gamepad {
+        device: u32,
+        event: GamepadInput,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordedFrame {
+    ///Time delta of the frame.
+    pub delta: f64,
+    pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+#[derive(Debug)]
+pub enum InputRecordingError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for InputRecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputRecordingError::Io(error) => write!(f, "{}", error),
+            InputRecordingError::Parse { line, message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputRecordingError {}
+
+impl From<std::io::Error> for InputRecordingError {
+    fn from(error: std::io::Error) -> Self {
+        InputRecordingError::Io(error)
+    }
+}
+
+impl InputRecording {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputRecordingError> {
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputRecordingError> {
+        Self::from_text(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for frame in &self.frames {
+            text += &format!("frame {:?}\n", frame.delta);
+            for event in &frame.events {
+                text += &event_to_line(event);
+                text += "\n";
+            }
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, InputRecordingError> {
+        let mut recording = InputRecording::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: String| InputRecordingError::Parse {
+                line: index + 1,
+                message,
+            };
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words[0] == "frame" {
+                let delta = parse_word(&words, 1).map_err(parse_error)?;
+                recording.frames.push(RecordedFrame {
+                    delta,
+                    events: Vec::new(),
+                });
+                continue;
+            }
+
+            let event = line_to_event(&words).map_err(parse_error)?;
+            match recording.frames.last_mut() {
+                Some(frame) => frame.events.push(event),
+                None => return Err(parse_error("event before any frame".to_string())),
+            }
+        }
+
+        Ok(recording)
+    }
+}
+
+fn state_name(state: ElementState) -> &'static str {
+    match state {
+        ElementState::Pressed => "Pressed",
+        ElementState::Released => "Released",
+    }
+}
+
+fn key_name(key: Option<KeyCode>) -> String {
+    match key {
+        Some(key) => key.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn button_name(button: winit::event::MouseButton) -> String {
+    match button {
+        winit::event::MouseButton::Other(index) => index.to_string(),
+        button => format!("{:?}", button),
+    }
+}
+
+fn scroll_words(delta: MouseScrollDelta) -> String {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => format!("line {:?} {:?}", x, y),
+        MouseScrollDelta::PixelDelta(position) => {
+            format!("pixel {:?} {:?}", position.x, position.y)
+        }
+    }
+}
+
+fn event_to_line(event: &RecordedEvent) -> String {
+    match *event {
+        RecordedEvent::Window { window, event } => {
+            let words = match event {
+                RecordedWindowEvent::KeyboardInput {
+                    device,
+                    scancode,
+                    state,
+                    key,
+                } => format!(
+                    "key {} {} {} {}",
+                    device,
+                    scancode,
+                    state_name(state),
+                    key_name(key)
+                ),
+                RecordedWindowEvent::MouseInput {
+                    device,
+                    state,
+                    button,
+                } => format!(
+                    "mouse_input {} {} {}",
+                    device,
+                    state_name(state),
+                    button_name(button)
+                ),
+                RecordedWindowEvent::MouseWheel { device, delta } => {
+                    format!("mouse_wheel {} {}", device, scroll_words(delta))
+                }
+                RecordedWindowEvent::CursorMoved { device, position } => {
+                    format!("cursor_moved {} {:?} {:?}", device, position.0, position.1)
+                }
+                RecordedWindowEvent::CursorEntered { device } => {
+                    format!("cursor_entered {}", device)
+                }
+                RecordedWindowEvent::CursorLeft { device } => format!("cursor_left {}", device),
+                RecordedWindowEvent::ReceivedCharacter { character } => {
+                    format!("character - {}", character as u32)
+                }
+                RecordedWindowEvent::ModifiersChanged { modifiers } if modifiers.is_empty() => {
+                    "modifiers - None".to_string()
+                }
+                RecordedWindowEvent::ModifiersChanged { modifiers } => {
+                    format!("modifiers - {}", modifiers)
+                }
+                RecordedWindowEvent::Focused { focused } => format!("focused - {}", focused),
+                RecordedWindowEvent::Touch {
+                    device,
+                    id,
+                    phase,
+                    position,
+                    force,
+                } => format!(
+                    "touch {} {} {:?} {:?} {:?} {}",
+                    device,
+                    id,
+                    phase,
+                    position.0,
+                    position.1,
+                    force.map_or("-".to_string(), |force| format!("{:?}", force))
+                ),
+                RecordedWindowEvent::TouchpadPressure {
+                    device,
+                    pressure,
+                    stage,
+                } => format!("touchpad_pressure {} {:?} {}", device, pressure, stage),
+            };
+            format!("window {} {}", window, words)
+        }
+        RecordedEvent::Device { device, event } => {
+            let words = match event {
+                RecordedDeviceEvent::Added => "added".to_string(),
+                RecordedDeviceEvent::Removed => "removed".to_string(),
+                RecordedDeviceEvent::MouseMotion { delta } => {
+                    format!("mouse_motion {:?} {:?}", delta.0, delta.1)
+                }
+                RecordedDeviceEvent::MouseWheel { delta } => {
+                    format!("mouse_wheel {}", scroll_words(delta))
+                }
+                RecordedDeviceEvent::Motion { axis, value } => {
+                    format!("motion {} {:?}", axis, value)
+                }
+                RecordedDeviceEvent::Button { button, state } => {
+                    format!("button {} {}", button, state_name(state))
+                }
+                RecordedDeviceEvent::Key {
+                    scancode,
+                    state,
+                    key,
+                } => format!("key {} {} {}", scancode, state_name(state), key_name(key)),
+                RecordedDeviceEvent::Text { codepoint } => format!("text {}", codepoint as u32),
+            };
+            format!("device {} {}", device, words)
+        }
+        RecordedEvent::Gamepad { device, event } => {
+            let words = match event {
+                GamepadInput::Connected => "connected".to_string(),
+                GamepadInput::Disconnected => "disconnected".to_string(),
+                GamepadInput::Button { button, state } => {
+                    format!("button {:?} {}", button, state_name(state))
+                }
+                GamepadInput::Axis { axis, value } => format!("axis {:?} {:?}", axis, value),
+            };
+            format!("gamepad {} {}", device, words)
+        }
+    }
+}
+
+fn word<'a>(words: &[&'a str], index: usize) -> Result<&'a str, String> {
+    words
+        .get(index)
+        .copied()
+        .ok_or_else(|| "too few words".to_string())
+}
+
+fn parse_word<T: std::str::FromStr>(words: &[&str], index: usize) -> Result<T, String> {
+    let word = word(words, index)?;
+    word.parse().map_err(|_| format!("invalid `{}`", word))
+}
+
+fn parse_state(words: &[&str], index: usize) -> Result<ElementState, String> {
+    match word(words, index)? {
+        "Pressed" => Ok(ElementState::Pressed),
+        "Released" => Ok(ElementState::Released),
+        state => Err(format!("invalid state `{}`", state)),
+    }
+}
+
+fn parse_key(words: &[&str], index: usize) -> Result<Option<KeyCode>, String> {
+    match word(words, index)? {
+        "-" => Ok(None),
+        key => key
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("unknown key `{}`", key)),
+    }
+}
+
+fn parse_button(words: &[&str], index: usize) -> Result<winit::event::MouseButton, String> {
+    use winit::event::MouseButton;
+    match word(words, index)? {
+        "Left" => Ok(MouseButton::Left),
+        "Middle" => Ok(MouseButton::Middle),
+        "Right" => Ok(MouseButton::Right),
+        _ => parse_word(words, index).map(MouseButton::Other),
+    }
+}
+
+fn parse_scroll(words: &[&str], index: usize) -> Result<MouseScrollDelta, String> {
+    match word(words, index)? {
+        "line" => Ok(MouseScrollDelta::LineDelta(
+            parse_word(words, index + 1)?,
+            parse_word(words, index + 2)?,
+        )),
+        "pixel" => Ok(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            parse_word(words, index + 1)?,
+            parse_word(words, index + 2)?,
+        ))),
+        kind => Err(format!("invalid scroll `{}`", kind)),
+    }
+}
+
+fn parse_phase(words: &[&str], index: usize) -> Result<TouchPhase, String> {
+    match word(words, index)? {
+        "Started" => Ok(TouchPhase::Started),
+        "Moved" => Ok(TouchPhase::Moved),
+        "Ended" => Ok(TouchPhase::Ended),
+        "Cancelled" => Ok(TouchPhase::Cancelled),
+        phase => Err(format!("invalid phase `{}`", phase)),
+    }
+}
+
+fn parse_gamepad_button(words: &[&str], index: usize) -> Result<GamepadButton, String> {
+    let word = word(words, index)?;
+    GamepadButton::ALL
+        .iter()
+        .find(|button| format!("{:?}", button) == word)
+        .copied()
+        .ok_or_else(|| format!("unknown gamepad button `{}`", word))
+}
+
+fn parse_gamepad_axis(words: &[&str], index: usize) -> Result<GamepadAxis, String> {
+    let word = word(words, index)?;
+    GamepadAxis::ALL
+        .iter()
+        .find(|axis| format!("{:?}", axis) == word)
+        .copied()
+        .ok_or_else(|| format!("unknown gamepad axis `{}`", word))
+}
+
+fn line_to_event(words: &[&str]) -> Result<RecordedEvent, String> {
+    match words[0] {
+        "window" => {
+            let window = parse_word(words, 1)?;
+            //Not from a device.
+            match word(words, 2)? {
+                "character" => {
+                    let character = parse_word::<u32>(words, 4).and_then(|codepoint| {
+                        char::from_u32(codepoint).ok_or_else(|| "invalid codepoint".to_string())
+                    })?;
+                    return Ok(RecordedEvent::Window {
+                        window,
+                        event: RecordedWindowEvent::ReceivedCharacter { character },
+                    });
+                }
+                "modifiers" => {
+                    let modifiers = match word(words, 4)? {
+                        "None" => Modifiers::NONE,
+                        modifiers => modifiers.parse()?,
+                    };
+                    return Ok(RecordedEvent::Window {
+                        window,
+                        event: RecordedWindowEvent::ModifiersChanged { modifiers },
+                    });
+                }
+                "focused" => {
+                    return Ok(RecordedEvent::Window {
+                        window,
+                        event: RecordedWindowEvent::Focused {
+                            focused: parse_word(words, 4)?,
+                        },
+                    });
+                }
+                _ => {}
+            }
+            let device = parse_word(words, 3)?;
+            let event = match word(words, 2)? {
+                "key" => RecordedWindowEvent::KeyboardInput {
+                    device,
+                    scancode: parse_word(words, 4)?,
+                    state: parse_state(words, 5)?,
+                    key: parse_key(words, 6)?,
+                },
+                "mouse_input" => RecordedWindowEvent::MouseInput {
+                    device,
+                    state: parse_state(words, 4)?,
+                    button: parse_button(words, 5)?,
+                },
+                "mouse_wheel" => RecordedWindowEvent::MouseWheel {
+                    device,
+                    delta: parse_scroll(words, 4)?,
+                },
+                "cursor_moved" => RecordedWindowEvent::CursorMoved {
+                    device,
+                    position: (parse_word(words, 4)?, parse_word(words, 5)?),
+                },
+                "cursor_entered" => RecordedWindowEvent::CursorEntered { device },
+                "cursor_left" => RecordedWindowEvent::CursorLeft { device },
+                "touch" => RecordedWindowEvent::Touch {
+                    device,
+                    id: parse_word(words, 4)?,
+                    phase: parse_phase(words, 5)?,
+                    position: (parse_word(words, 6)?, parse_word(words, 7)?),
+                    force: match word(words, 8)? {
+                        "-" => None,
+                        _ => Some(parse_word(words, 8)?),
+                    },
+                },
+                "touchpad_pressure" => RecordedWindowEvent::TouchpadPressure {
+                    device,
+                    pressure: parse_word(words, 4)?,
+                    stage: parse_word(words, 5)?,
+                },
+                kind => return Err(format!("unknown window event `{}`", kind)),
+            };
+            Ok(RecordedEvent::Window { window, event })
+        }
+        "device" => {
+            let device = parse_word(words, 1)?;
+            let event = match word(words, 2)? {
+                "added" => RecordedDeviceEvent::Added,
+                "removed" => RecordedDeviceEvent::Removed,
+                "mouse_motion" => RecordedDeviceEvent::MouseMotion {
+                    delta: (parse_word(words, 3)?, parse_word(words, 4)?),
+                },
+                "mouse_wheel" => RecordedDeviceEvent::MouseWheel {
+                    delta: parse_scroll(words, 3)?,
+                },
+                "motion" => RecordedDeviceEvent::Motion {
+                    axis: parse_word(words, 3)?,
+                    value: parse_word(words, 4)?,
+                },
+                "button" => RecordedDeviceEvent::Button {
+                    button: parse_word(words, 3)?,
+                    state: parse_state(words, 4)?,
+                },
+                "key" => RecordedDeviceEvent::Key {
+                    scancode: parse_word(words, 3)?,
+                    state: parse_state(words, 4)?,
+                    key: parse_key(words, 5)?,
+                },
+                "text" => RecordedDeviceEvent::Text {
+                    codepoint: parse_word::<u32>(words, 3).and_then(|codepoint| {
+                        char::from_u32(codepoint).ok_or_else(|| "invalid codepoint".to_string())
+                    })?,
+                },
+                kind => return Err(format!("unknown device event `{}`", kind)),
+            };
+            Ok(RecordedEvent::Device { device, event })
+        }
+        "gamepad" => {
+            let device = parse_word(words, 1)?;
+            let event = match word(words, 2)? {
+                "connected" => GamepadInput::Connected,
+                "disconnected" => GamepadInput::Disconnected,
+                "button" => GamepadInput::Button {
+                    button: parse_gamepad_button(words, 3)?,
+                    state: parse_state(words, 4)?,
+                },
+                "axis" => GamepadInput::Axis {
+                    axis: parse_gamepad_axis(words, 3)?,
+                    value: parse_word(words, 4)?,
+                },
+                kind => return Err(format!("unknown gamepad event `{}`", kind)),
+            };
+            Ok(RecordedEvent::Gamepad { device, event })
+        }
+        kind => Err(format!("unknown event `{}`", kind)),
+    }
+}
+
+///Gives indexes to windows and devices in order of appearance.
+pub(super) struct InputRecorder {
+    recording: InputRecording,
+    windows: HashMap<WindowId, u32>,
+    devices: HashMap<InputDeviceId, u32>,
+}
+
+impl InputRecorder {
+    pub(super) fn new() -> Self {
+        Self {
+            recording: InputRecording::default(),
+            windows: HashMap::new(),
+            devices: HashMap::new(),
+        }
+    }
+
+    pub(super) fn finish(self) -> InputRecording {
+        self.recording
+    }
+
+    pub(super) fn begin_frame(&mut self, delta: f64) {
+        self.recording.frames.push(RecordedFrame {
+            delta,
+            events: Vec::new(),
+        });
+    }
+
+    fn push(&mut self, event: RecordedEvent) {
+        //Events before the first frame would be replayed at a wrong time.
+        if let Some(frame) = self.recording.frames.last_mut() {
+            frame.events.push(event);
+        }
+    }
+
+    fn window_index(&mut self, window_id: WindowId) -> u32 {
+        let len = self.windows.len() as u32;
+        *self.windows.entry(window_id).or_insert(len)
+    }
+
+    fn device_index(&mut self, device_id: impl Into<InputDeviceId>) -> u32 {
+        let len = self.devices.len() as u32;
+        *self.devices.entry(device_id.into()).or_insert(len)
+    }
+
+    pub(super) fn record_window_input(&mut self, window_id: WindowId, input: &WindowEvent) {
+        let event = match *input {
+            WindowEvent::KeyboardInput {
+                device_id, input, ..
+            } => RecordedWindowEvent::KeyboardInput {
+                device: self.device_index(device_id),
+                scancode: input.scancode,
+                state: input.state,
+                key: input.virtual_keycode.map(KeyCode::from),
+            },
+            WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+                ..
+            } => RecordedWindowEvent::MouseInput {
+                device: self.device_index(device_id),
+                state,
+                button,
+            },
+            WindowEvent::MouseWheel {
+                device_id, delta, ..
+            } => RecordedWindowEvent::MouseWheel {
+                device: self.device_index(device_id),
+                delta,
+            },
+            WindowEvent::CursorMoved {
+                device_id,
+                position,
+                ..
+            } => RecordedWindowEvent::CursorMoved {
+                device: self.device_index(device_id),
+                position: (position.x, position.y),
+            },
+            WindowEvent::CursorEntered { device_id } => RecordedWindowEvent::CursorEntered {
+                device: self.device_index(device_id),
+            },
+            WindowEvent::CursorLeft { device_id } => RecordedWindowEvent::CursorLeft {
+                device: self.device_index(device_id),
+            },
+            WindowEvent::ReceivedCharacter(character) => {
+                RecordedWindowEvent::ReceivedCharacter { character }
+            }
+            WindowEvent::ModifiersChanged(state) => RecordedWindowEvent::ModifiersChanged {
+                modifiers: state.into(),
+            },
+            WindowEvent::Focused(focused) => RecordedWindowEvent::Focused { focused },
+            WindowEvent::Touch(touch) => RecordedWindowEvent::Touch {
+                device: self.device_index(touch.device_id),
+                id: touch.id,
+                phase: touch.phase,
+                position: (touch.location.x, touch.location.y),
+                force: touch.force.map(|force| force.normalized()),
+            },
+            WindowEvent::TouchpadPressure {
+                device_id,
+                pressure,
+                stage,
+            } => RecordedWindowEvent::TouchpadPressure {
+                device: self.device_index(device_id),
+                pressure,
+                stage,
+            },
+            _ => return,
+        };
+        let window = self.window_index(window_id);
+        self.push(RecordedEvent::Window { window, event });
+    }
+
+    pub(super) fn record_device_input(&mut self, device_id: DeviceId, input: &DeviceEvent) {
+        let event = match *input {
+            DeviceEvent::Added => RecordedDeviceEvent::Added,
+            DeviceEvent::Removed => RecordedDeviceEvent::Removed,
+            DeviceEvent::MouseMotion { delta } => RecordedDeviceEvent::MouseMotion { delta },
+            DeviceEvent::MouseWheel { delta } => RecordedDeviceEvent::MouseWheel { delta },
+            DeviceEvent::Motion { axis, value } => RecordedDeviceEvent::Motion { axis, value },
+            DeviceEvent::Button { button, state } => RecordedDeviceEvent::Button { button, state },
+            DeviceEvent::Key(input) => RecordedDeviceEvent::Key {
+                scancode: input.scancode,
+                state: input.state,
+                key: input.virtual_keycode.map(KeyCode::from),
+            },
+            DeviceEvent::Text { codepoint } => RecordedDeviceEvent::Text { codepoint },
+        };
+        let device = self.device_index(device_id);
+        self.push(RecordedEvent::Device { device, event });
+    }
+
+    pub(super) fn record_gamepad_input(&mut self, device_id: InputDeviceId, input: GamepadInput) {
+        let device = self.device_index(device_id);
+        self.push(RecordedEvent::Gamepad {
+            device,
+            event: input,
+        });
+    }
+}
+
+#[allow(deprecated)]
+fn keyboard_input(scancode: u32, state: ElementState, key: Option<KeyCode>) -> KeyboardInput {
+    KeyboardInput {
+        scancode,
+        state,
+        virtual_keycode: key.map(Into::into),
+        modifiers: ModifiersState::empty(),
+    }
+}
+
+impl Inputs {
+    ///Starts at the next frame.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(InputRecorder::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recorder.take().map(InputRecorder::finish)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub(crate) fn record_frame(&mut self, delta: f64) {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.begin_frame(delta);
+        }
+    }
+
+    ///window_ids are windows for recorded window indexes. Unknown indexes go to the first one.
+    #[allow(deprecated)]
+    pub(super) fn replay_event(&mut self, event: &RecordedEvent, window_ids: &[WindowId]) {
+        //Recorded ids are only used to tell devices apart.
+        let dummy_device_id = unsafe { DeviceId::dummy() };
+
+        match *event {
+            RecordedEvent::Window { window, event } => {
+                let window_id = match window_ids.get(window as usize).or(window_ids.first()) {
+                    Some(window_id) => *window_id,
+                    None => return,
+                };
+                let (device, input) = match event {
+                    RecordedWindowEvent::KeyboardInput {
+                        device,
+                        scancode,
+                        state,
+                        key,
+                    } => (
+                        device,
+                        WindowEvent::KeyboardInput {
+                            device_id: dummy_device_id,
+                            input: keyboard_input(scancode, state, key),
+                            is_synthetic: false,
+                        },
+                    ),
+                    RecordedWindowEvent::MouseInput {
+                        device,
+                        state,
+                        button,
+                    } => (
+                        device,
+                        WindowEvent::MouseInput {
+                            device_id: dummy_device_id,
+                            state,
+                            button,
+                            modifiers: ModifiersState::empty(),
+                        },
+                    ),
+                    RecordedWindowEvent::MouseWheel { device, delta } => (
+                        device,
+                        WindowEvent::MouseWheel {
+                            device_id: dummy_device_id,
+                            delta,
+                            phase: TouchPhase::Moved,
+                            modifiers: ModifiersState::empty(),
+                        },
+                    ),
+                    RecordedWindowEvent::CursorMoved { device, position } => (
+                        device,
+                        WindowEvent::CursorMoved {
+                            device_id: dummy_device_id,
+                            position: PhysicalPosition::new(position.0, position.1),
+                            modifiers: ModifiersState::empty(),
+                        },
+                    ),
+                    RecordedWindowEvent::CursorEntered { device } => (
+                        device,
+                        WindowEvent::CursorEntered {
+                            device_id: dummy_device_id,
+                        },
+                    ),
+                    RecordedWindowEvent::CursorLeft { device } => (
+                        device,
+                        WindowEvent::CursorLeft {
+                            device_id: dummy_device_id,
+                        },
+                    ),
+                    RecordedWindowEvent::ReceivedCharacter { character } => {
+                        self.handle_window_input_as(
+                            window_id,
+                            WindowEvent::ReceivedCharacter(character),
+                            None,
+                        );
+                        return;
+                    }
+                    RecordedWindowEvent::ModifiersChanged { modifiers } => {
+                        self.handle_window_input_as(
+                            window_id,
+                            WindowEvent::ModifiersChanged(modifiers.into()),
+                            None,
+                        );
+                        return;
+                    }
+                    RecordedWindowEvent::Touch {
+                        device,
+                        id,
+                        phase,
+                        position,
+                        force,
+                    } => (
+                        device,
+                        WindowEvent::Touch(Touch {
+                            device_id: dummy_device_id,
+                            phase,
+                            location: PhysicalPosition::new(position.0, position.1),
+                            force: force.map(Force::Normalized),
+                            id,
+                        }),
+                    ),
+                    RecordedWindowEvent::TouchpadPressure {
+                        device,
+                        pressure,
+                        stage,
+                    } => (
+                        device,
+                        WindowEvent::TouchpadPressure {
+                            device_id: dummy_device_id,
+                            pressure,
+                            stage,
+                        },
+                    ),
+                    RecordedWindowEvent::Focused { focused } => {
+                        self.handle_window_input_as(window_id, WindowEvent::Focused(focused), None);
+                        return;
+                    }
+                };
+                self.handle_window_input_as(
+                    window_id,
+                    input,
+                    Some(InputDeviceId::Replayed(device)),
+                );
+            }
+            RecordedEvent::Device { device, event } => {
+                let input = match event {
+                    RecordedDeviceEvent::Added => DeviceEvent::Added,
+                    RecordedDeviceEvent::Removed => DeviceEvent::Removed,
+                    RecordedDeviceEvent::MouseMotion { delta } => {
+                        DeviceEvent::MouseMotion { delta }
+                    }
+                    RecordedDeviceEvent::MouseWheel { delta } => DeviceEvent::MouseWheel { delta },
+                    RecordedDeviceEvent::Motion { axis, value } => {
+                        DeviceEvent::Motion { axis, value }
+                    }
+                    RecordedDeviceEvent::Button { button, state } => {
+                        DeviceEvent::Button { button, state }
+                    }
+                    RecordedDeviceEvent::Key {
+                        scancode,
+                        state,
+                        key,
+                    } => DeviceEvent::Key(keyboard_input(scancode, state, key)),
+                    RecordedDeviceEvent::Text { codepoint } => DeviceEvent::Text { codepoint },
+                };
+                self.handle_device_input_as(InputDeviceId::Replayed(device), input);
+            }
+            RecordedEvent::Gamepad { device, event } => {
+                self.handle_gamepad_input_as(InputDeviceId::Replayed(device), event)
+            }
+        }
+    }
+}
+
+///Feeds a recording to inputs frame by frame.
+pub struct InputReplay {
+    recording: InputRecording,
+    next_frame: usize,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    ///Starts the next frame of inputs with recorded events. Gives the recorded delta.
+    pub fn advance(&mut self, inputs: &mut Inputs, window_ids: &[WindowId]) -> Option<f64> {
+        let frame = self.recording.frames.get(self.next_frame)?;
+        self.next_frame += 1;
+
+        inputs.set_time(inputs.time() + frame.delta);
+        inputs.pre_update();
+        for event in &frame.events {
+            inputs.replay_event(event, window_ids);
+        }
+        Some(frame.delta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[allow(deprecated)]
+    fn live_session(inputs: &mut Inputs, window_id: WindowId) {
+        let device_id = unsafe { DeviceId::dummy() };
+        let key = |state| WindowEvent::KeyboardInput {
+            device_id,
+            input: KeyboardInput {
+                scancode: 17,
+                state,
+                virtual_keycode: Some(VirtualKeyCode::W),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        };
+
+        inputs.pre_update();
+        inputs.record_frame(0.016);
+        inputs.handle_window_input(window_id, key(ElementState::Pressed));
+        inputs.handle_window_input(window_id, WindowEvent::ReceivedCharacter('w'));
+        inputs.handle_window_input(
+            window_id,
+            WindowEvent::ModifiersChanged(ModifiersState::CTRL),
+        );
+        inputs.handle_device_input(
+            device_id,
+            DeviceEvent::MouseWheel {
+                delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.5, -2.25)),
+            },
+        );
+
+        inputs.pre_update();
+        inputs.record_frame(0.017);
+        inputs.handle_window_input(window_id, key(ElementState::Released));
+    }
+
+    #[allow(deprecated)]
+    fn focus_lost_session(inputs: &mut Inputs, window_id: WindowId) {
+        let device_id = unsafe { DeviceId::dummy() };
+
+        inputs.pre_update();
+        inputs.record_frame(0.016);
+        inputs.handle_window_input(
+            window_id,
+            WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: 17,
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::W),
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: false,
+            },
+        );
+
+        //Released in another window, so this one never hears it.
+        inputs.pre_update();
+        inputs.record_frame(0.016);
+        inputs.handle_window_input(window_id, WindowEvent::Focused(false));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.start_recording();
+        live_session(&mut inputs, window_id);
+        let recording = inputs.stop_recording().unwrap();
+
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.frames[0].events.len(), 4);
+        assert_eq!(
+            InputRecording::from_text(&recording.to_text()).unwrap(),
+            recording
+        );
+    }
+
+    #[test]
+    fn replays_same_states() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.start_recording();
+        live_session(&mut inputs, window_id);
+        let recording = inputs.stop_recording().unwrap();
+
+        let mut replayed = Inputs::new();
+        let mut replay = InputReplay::new(recording);
+
+        assert_eq!(replay.advance(&mut replayed, &[window_id]), Some(0.016));
+        let keyboard = replayed.window_keyboard(window_id).unwrap();
+        assert!(keyboard.is_just_pressed(KeyCode::W));
+        assert_eq!(replayed.text_input(window_id).unwrap().text(), "w");
+        assert_eq!(
+            replayed.window_modifiers(window_id),
+            Some(Modifiers::CONTROL)
+        );
+
+        assert_eq!(replay.advance(&mut replayed, &[window_id]), Some(0.017));
+        let keyboard = replayed.window_keyboard(window_id).unwrap();
+        assert!(keyboard.is_just_released(KeyCode::W));
+
+        assert!(replay.is_finished());
+        assert_eq!(replay.advance(&mut replayed, &[window_id]), None);
+    }
+
+    #[test]
+    fn replays_touches() {
+        let window_id = unsafe { WindowId::dummy() };
+        let device_id = unsafe { DeviceId::dummy() };
+        let touch = |phase, x, force| {
+            WindowEvent::Touch(Touch {
+                device_id,
+                phase,
+                location: PhysicalPosition::new(x, 0.0),
+                force,
+                id: 3,
+            })
+        };
+
+        let mut inputs = Inputs::new();
+        inputs.start_recording();
+        //As the application does, so start times match the replay.
+        inputs.set_time(0.016);
+        inputs.pre_update();
+        inputs.record_frame(0.016);
+        inputs.handle_window_input(window_id, touch(TouchPhase::Started, 0.0, None));
+        inputs.handle_window_input(
+            window_id,
+            touch(TouchPhase::Moved, 30.0, Some(Force::Normalized(0.5))),
+        );
+        inputs.handle_window_input(
+            window_id,
+            WindowEvent::TouchpadPressure {
+                device_id,
+                pressure: 0.25,
+                stage: 1,
+            },
+        );
+        let recording = inputs.stop_recording().unwrap();
+        assert_eq!(recording.frames[0].events.len(), 3);
+
+        let recording = InputRecording::from_text(&recording.to_text()).unwrap();
+        let mut replayed = Inputs::new();
+        InputReplay::new(recording).advance(&mut replayed, &[window_id]);
+
+        let live = inputs.touches(window_id).unwrap();
+        let touches = replayed.touches(window_id).unwrap();
+        assert_eq!(touches.points(), live.points());
+        assert_eq!(touches.gestures(), live.gestures());
+        assert_eq!(touches.get(3).unwrap().force, Some(0.5));
+        assert_eq!(touches.touchpad_pressure(), 0.25);
+    }
+
+    #[test]
+    fn replays_focus_loss() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.start_recording();
+        focus_lost_session(&mut inputs, window_id);
+        let recording = inputs.stop_recording().unwrap();
+        assert!(!inputs
+            .window_keyboard(window_id)
+            .unwrap()
+            .is_pressed(KeyCode::W));
+
+        let recording = InputRecording::from_text(&recording.to_text()).unwrap();
+        assert_eq!(
+            recording.frames[1].events,
+            vec![RecordedEvent::Window {
+                window: 0,
+                event: RecordedWindowEvent::Focused { focused: false },
+            }]
+        );
+
+        let mut replayed = Inputs::new();
+        let mut replay = InputReplay::new(recording);
+        replay.advance(&mut replayed, &[window_id]);
+        assert!(replayed
+            .window_keyboard(window_id)
+            .unwrap()
+            .is_pressed(KeyCode::W));
+        replay.advance(&mut replayed, &[window_id]);
+        let keyboard = replayed.window_keyboard(window_id).unwrap();
+        assert!(!keyboard.is_pressed(KeyCode::W));
+        assert!(keyboard.is_just_released(KeyCode::W));
+    }
+
+    #[test]
+    fn replays_gamepads() {
+        let mut inputs = Inputs::new();
+        inputs.start_recording();
+        inputs.pre_update();
+        inputs.record_frame(0.016);
+        inputs.handle_device_input(
+            unsafe { DeviceId::dummy() },
+            DeviceEvent::MouseMotion { delta: (1.0, 0.0) },
+        );
+        inputs.handle_gamepad_input(3, GamepadInput::Connected);
+        inputs.handle_gamepad_input(
+            3,
+            GamepadInput::Button {
+                button: GamepadButton::East,
+                state: ElementState::Pressed,
+            },
+        );
+        inputs.handle_gamepad_input(
+            3,
+            GamepadInput::Axis {
+                axis: GamepadAxis::RightTrigger,
+                value: 0.75,
+            },
+        );
+        let recording = inputs.stop_recording().unwrap();
+        //Indexes are shared with winit devices, so they don't collide when replayed.
+        assert_eq!(
+            recording.frames[0].events[1],
+            RecordedEvent::Gamepad {
+                device: 1,
+                event: GamepadInput::Connected
+            }
+        );
+
+        let recording = InputRecording::from_text(&recording.to_text()).unwrap();
+        let mut replayed = Inputs::new();
+        InputReplay::new(recording).advance(&mut replayed, &[]);
+
+        let gamepad = replayed.gamepad(None).unwrap();
+        assert!(gamepad.is_just_pressed(GamepadButton::East));
+        assert_eq!(gamepad.raw_axis(GamepadAxis::RightTrigger), 0.75);
+        assert_eq!(
+            replayed.gamepad_events(),
+            &[GamepadEvent::Connected(InputDeviceId::Replayed(1))]
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,279 @@
+//!Synthetic inputs for tests. They go through same handlers as winit events.
+use super::*;
+
+use cgmath::*;
+
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceEvent, DeviceId, ElementState, KeyboardInput, ModifiersState, MouseScrollDelta,
+        Touch, TouchPhase, WindowEvent,
+    },
+    window::WindowId,
+};
+
+impl InputDeviceId {
+    pub const SIMULATED_KEYBOARD: InputDeviceId = InputDeviceId::Simulated(0);
+    pub const SIMULATED_MOUSE: InputDeviceId = InputDeviceId::Simulated(1);
+}
+
+impl From<ButtonState> for ElementState {
+    fn from(state: ButtonState) -> Self {
+        match state {
+            ButtonState::Pressed => ElementState::Pressed,
+            ButtonState::Released => ElementState::Released,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl Inputs {
+    ///Ends the current frame. States like just pressed are cleared.
+    pub fn advance_frame(&mut self) {
+        self.pre_update();
+    }
+
+    ///Ends the current frame, and the next one starts delta seconds later.
+    pub fn advance_frame_by(&mut self, delta: f64) {
+        self.set_time(self.time() + delta);
+        self.pre_update();
+    }
+
+    ///Scancode is where the key is on a US layout. Keys out of the main typing area press no scancode.
+    pub fn simulate_key(&mut self, window_id: WindowId, key: KeyCode, state: ButtonState) {
+        let scancode = key.scancode().unwrap_or(NO_SCANCODE);
+        self.simulate_key_at(window_id, scancode, Some(key), state);
+    }
+
+    ///Key of other layouts, e.g. Z at the scancode of W on AZERTY.
+    pub fn simulate_key_at(
+        &mut self,
+        window_id: WindowId,
+        scancode: u32,
+        key: Option<KeyCode>,
+        state: ButtonState,
+    ) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode,
+                    state: state.into(),
+                    virtual_keycode: key.map(Into::into),
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: true,
+            },
+            Some(InputDeviceId::SIMULATED_KEYBOARD),
+        );
+    }
+
+    pub fn simulate_mouse_button(
+        &mut self,
+        window_id: WindowId,
+        button: MouseButton,
+        state: ButtonState,
+    ) {
+        let button = match button {
+            MouseButton::Left => winit::event::MouseButton::Left,
+            MouseButton::Middle => winit::event::MouseButton::Middle,
+            MouseButton::Right => winit::event::MouseButton::Right,
+            MouseButton::Other(index) => winit::event::MouseButton::Other(index as u16),
+        };
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::MouseInput {
+                device_id: unsafe { DeviceId::dummy() },
+                state: state.into(),
+                button,
+                modifiers: ModifiersState::empty(),
+            },
+            Some(InputDeviceId::SIMULATED_MOUSE),
+        );
+    }
+
+    pub fn simulate_mouse_wheel(&mut self, window_id: WindowId, lines: f32) {
+        self.simulate_mouse_scroll(window_id, MouseScrollDelta::LineDelta(0.0, lines));
+    }
+
+    pub fn simulate_mouse_scroll(&mut self, window_id: WindowId, delta: MouseScrollDelta) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::MouseWheel {
+                device_id: unsafe { DeviceId::dummy() },
+                delta,
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            },
+            Some(InputDeviceId::SIMULATED_MOUSE),
+        );
+    }
+
+    ///Modifiers told by the window, apart from modifier keys.
+    pub fn simulate_modifiers(&mut self, window_id: WindowId, modifiers: Modifiers) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::ModifiersChanged(modifiers.into()),
+            None,
+        );
+    }
+
+    ///Losing focus releases keys and buttons held in the window.
+    pub fn simulate_focus(&mut self, window_id: WindowId, focused: bool) {
+        self.handle_window_input_as(window_id, WindowEvent::Focused(focused), None);
+    }
+
+    pub fn simulate_character(&mut self, window_id: WindowId, character: char) {
+        self.handle_window_input_as(window_id, WindowEvent::ReceivedCharacter(character), None);
+    }
+
+    ///Composition of input method. None ends it without committing.
+    pub fn simulate_preedit(&mut self, window_id: WindowId, preedit: Option<Preedit>) {
+        self.window_input_mut(window_id)
+            .text_input_mut()
+            .set_preedit(preedit);
+    }
+
+    ///Input method commits composed text.
+    pub fn simulate_commit(&mut self, window_id: WindowId, text: &str) {
+        self.window_input_mut(window_id)
+            .text_input_mut()
+            .commit(text);
+    }
+
+    ///Raw motion of the simulated mouse, which is not bound to windows.
+    pub fn simulate_mouse_motion(&mut self, delta: Vector2<f32>) {
+        self.handle_device_input_as(
+            InputDeviceId::SIMULATED_MOUSE,
+            DeviceEvent::MouseMotion {
+                delta: (delta.x as f64, delta.y as f64),
+            },
+        );
+    }
+
+    pub fn simulate_cursor_move(&mut self, window_id: WindowId, position: Point2<f32>) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::CursorMoved {
+                device_id: unsafe { DeviceId::dummy() },
+                position: PhysicalPosition::new(position.x as f64, position.y as f64),
+                modifiers: ModifiersState::empty(),
+            },
+            Some(InputDeviceId::SIMULATED_MOUSE),
+        );
+    }
+
+    pub fn simulate_touch(
+        &mut self,
+        window_id: WindowId,
+        id: u64,
+        phase: TouchPhase,
+        position: Point2<f32>,
+    ) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::Touch(Touch {
+                device_id: unsafe { DeviceId::dummy() },
+                phase,
+                location: PhysicalPosition::new(position.x as f64, position.y as f64),
+                force: None,
+                id,
+            }),
+            None,
+        );
+    }
+
+    ///As if the window applied it.
+    pub fn simulate_cursor_mode(&mut self, window_id: WindowId, mode: CursorMode) {
+        self.handle_cursor_request(window_id, CursorRequest::Mode(mode));
+    }
+
+    pub fn simulate_cursor_enter(&mut self, window_id: WindowId) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::CursorEntered {
+                device_id: unsafe { DeviceId::dummy() },
+            },
+            Some(InputDeviceId::SIMULATED_MOUSE),
+        );
+    }
+
+    pub fn simulate_cursor_leave(&mut self, window_id: WindowId) {
+        self.handle_window_input_as(
+            window_id,
+            WindowEvent::CursorLeft {
+                device_id: unsafe { DeviceId::dummy() },
+            },
+            Some(InputDeviceId::SIMULATED_MOUSE),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn simulated_states() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_key(window_id, KeyCode::W, ButtonState::Pressed);
+        inputs.simulate_mouse_button(window_id, MouseButton::Left, ButtonState::Pressed);
+        inputs.simulate_cursor_enter(window_id);
+        inputs.simulate_cursor_move(window_id, point2(3.0, 4.0));
+        inputs.simulate_mouse_motion(vec2(1.0, 2.0));
+        inputs.simulate_mouse_motion(vec2(1.0, 0.0));
+
+        assert!(inputs
+            .window_keyboard(window_id)
+            .unwrap()
+            .is_just_pressed(KeyCode::W));
+        assert!(inputs
+            .window_mouse(window_id)
+            .unwrap()
+            .is_pressed(MouseButton::Left));
+        assert!(inputs.cursor(window_id).unwrap().is_just_entered());
+        assert_eq!(
+            inputs.cursor(window_id).unwrap().position(),
+            point2(3.0, 4.0)
+        );
+        assert_eq!(inputs.device_mouse(None).unwrap().motion(), vec2(2.0, 2.0));
+
+        inputs.advance_frame();
+        inputs.simulate_key(window_id, KeyCode::W, ButtonState::Released);
+        assert!(inputs
+            .window_keyboard(window_id)
+            .unwrap()
+            .is_just_released(KeyCode::W));
+        assert_eq!(inputs.device_mouse(None).unwrap().motion(), Vector2::zero());
+    }
+
+    #[test]
+    fn focus_lost_releases() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_key(window_id, KeyCode::W, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::LShift, ButtonState::Pressed);
+        inputs.simulate_mouse_button(window_id, MouseButton::Left, ButtonState::Pressed);
+        inputs.advance_frame();
+
+        inputs.simulate_focus(window_id, false);
+        let keyboard = inputs.window_keyboard(window_id).unwrap();
+        assert!(keyboard.is_just_released(KeyCode::W));
+        assert!(!keyboard.is_physical_pressed(KeyCode::W));
+        assert!(inputs
+            .window_mouse(window_id)
+            .unwrap()
+            .is_just_released(MouseButton::Left));
+        assert_eq!(inputs.window_modifiers(window_id), Some(Modifiers::NONE));
+
+        inputs.advance_frame();
+        assert!(!inputs
+            .window_keyboard(window_id)
+            .unwrap()
+            .is_pressed(KeyCode::W));
+    }
+}
@@ -0,0 +1,303 @@
+//!Text typed into a window, for building text fields.
+use super::*;
+
+use std::cell::RefCell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextEditKey {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Tab,
+    Escape,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+}
+
+///Editing key pressed with modifiers. Repeated while the key is held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    pub key: TextEditKey,
+    pub modifiers: Modifiers,
+}
+
+impl TextEdit {
+    ///Moves or deletes by word.
+    pub fn by_word(&self) -> bool {
+        self.modifiers.contains(Modifiers::CONTROL)
+    }
+
+    ///Extends selection while moving.
+    pub fn selects(&self) -> bool {
+        self.modifiers.contains(Modifiers::SHIFT)
+    }
+}
+
+impl TextEditKey {
+    fn from_key(key: KeyCode, modifiers: Modifiers) -> Option<Self> {
+        let shortcut =
+            modifiers.contains(Modifiers::CONTROL) || modifiers.contains(Modifiers::LOGO);
+        let edit_key = match key {
+            KeyCode::Back => TextEditKey::Backspace,
+            KeyCode::Delete => TextEditKey::Delete,
+            KeyCode::Left => TextEditKey::Left,
+            KeyCode::Right => TextEditKey::Right,
+            KeyCode::Up => TextEditKey::Up,
+            KeyCode::Down => TextEditKey::Down,
+            KeyCode::Home => TextEditKey::Home,
+            KeyCode::End => TextEditKey::End,
+            KeyCode::PageUp => TextEditKey::PageUp,
+            KeyCode::PageDown => TextEditKey::PageDown,
+            KeyCode::Return | KeyCode::NumpadEnter => TextEditKey::Enter,
+            KeyCode::Tab => TextEditKey::Tab,
+            KeyCode::Escape => TextEditKey::Escape,
+            KeyCode::Copy => TextEditKey::Copy,
+            KeyCode::Cut => TextEditKey::Cut,
+            KeyCode::Paste => TextEditKey::Paste,
+            KeyCode::A if shortcut => TextEditKey::SelectAll,
+            KeyCode::C if shortcut => TextEditKey::Copy,
+            KeyCode::X if shortcut => TextEditKey::Cut,
+            KeyCode::V if shortcut => TextEditKey::Paste,
+            KeyCode::Z if shortcut && modifiers.contains(Modifiers::SHIFT) => TextEditKey::Redo,
+            KeyCode::Z if shortcut => TextEditKey::Undo,
+            KeyCode::Y if shortcut => TextEditKey::Redo,
+            _ => return None,
+        };
+        Some(edit_key)
+    }
+}
+
+///Typed character or editing key, in the order they came.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEvent {
+    Insert(char),
+    Edit(TextEdit),
+}
+
+///Text being composed by an input method, before it's committed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    ///Byte range of cursor or selection in text.
+    pub cursor: Option<(usize, usize)>,
+}
+
+pub struct TextInput {
+    events: Vec<TextEvent>,
+    text: String,
+    edits: Vec<TextEdit>,
+    preedit: Option<Preedit>,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            text: String::with_capacity(8),
+            edits: Vec::new(),
+            preedit: None,
+        }
+    }
+
+    ///Characters and edits at this frame in order. Apply these to keep edits between characters right.
+    pub fn events(&self) -> &[TextEvent] {
+        &self.events
+    }
+
+    ///Characters typed at this frame. Control characters are given as edits instead.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    ///Editing keys pressed at this frame in order.
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+
+    ///Some while composing. Kept across frames.
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+}
+
+impl TextInput {
+    pub(crate) fn pre_update(&mut self) {
+        self.events.clear();
+        self.text.clear();
+        self.edits.clear();
+    }
+
+    pub(crate) fn handle_character(&mut self, character: char) {
+        if !character.is_control() {
+            self.events.push(TextEvent::Insert(character));
+            self.text.push(character);
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyCode, modifiers: Modifiers) {
+        if let Some(key) = TextEditKey::from_key(key, modifiers) {
+            let edit = TextEdit { key, modifiers };
+            self.events.push(TextEvent::Edit(edit));
+            self.edits.push(edit);
+        }
+    }
+
+    ///winit doesn't tell composition yet, so this is set by simulation only.
+    pub(crate) fn set_preedit(&mut self, preedit: Option<Preedit>) {
+        self.preedit = preedit;
+    }
+
+    pub(crate) fn commit(&mut self, text: &str) {
+        self.preedit = None;
+        self.events.extend(text.chars().map(TextEvent::Insert));
+        self.text.push_str(text);
+    }
+}
+
+///Connects copy and paste to the system clipboard.
+pub trait Clipboard {
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: &str);
+}
+
+///Only shared inside the application. Used until a system clipboard is set.
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    text: Option<String>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.text = Some(text.to_string());
+    }
+}
+
+pub(super) type ClipboardCell = RefCell<Box<dyn Clipboard>>;
+
+impl Inputs {
+    pub fn set_clipboard(&mut self, clipboard: impl Clipboard + 'static) {
+        self.clipboard = RefCell::new(Box::new(clipboard));
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        self.clipboard.borrow_mut().get()
+    }
+
+    pub fn set_clipboard_text(&self, text: &str) {
+        self.clipboard.borrow_mut().set(text);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use winit::window::WindowId;
+
+    #[test]
+    fn characters_and_edits() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_character(window_id, 'h');
+        inputs.simulate_character(window_id, '\u{8}');
+        inputs.simulate_key(window_id, KeyCode::Back, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::LControl, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::Left, ButtonState::Pressed);
+        inputs.simulate_key(window_id, KeyCode::V, ButtonState::Pressed);
+
+        let text_input = inputs.text_input(window_id).unwrap();
+        assert_eq!(text_input.text(), "h");
+        let edits: Vec<TextEditKey> = text_input.edits().iter().map(|edit| edit.key).collect();
+        assert_eq!(
+            edits,
+            vec![
+                TextEditKey::Backspace,
+                TextEditKey::Left,
+                TextEditKey::Paste
+            ]
+        );
+        assert!(text_input.edits()[1].by_word());
+
+        inputs.advance_frame();
+        let text_input = inputs.text_input(window_id).unwrap();
+        assert_eq!(text_input.text(), "");
+        assert!(text_input.edits().is_empty());
+    }
+
+    #[test]
+    fn interleaved_in_order() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_character(window_id, 'a');
+        inputs.simulate_character(window_id, 'b');
+        inputs.simulate_key(window_id, KeyCode::Back, ButtonState::Pressed);
+        inputs.simulate_character(window_id, 'c');
+
+        let mut text = String::new();
+        for event in inputs.text_input(window_id).unwrap().events() {
+            match event {
+                TextEvent::Insert(character) => text.push(*character),
+                TextEvent::Edit(edit) if edit.key == TextEditKey::Backspace => {
+                    text.pop();
+                }
+                TextEvent::Edit(_) => {}
+            }
+        }
+        assert_eq!(text, "ac");
+    }
+
+    #[test]
+    fn preedit_then_commit() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_preedit(
+            window_id,
+            Some(Preedit {
+                text: "にほ".to_string(),
+                cursor: Some((6, 6)),
+            }),
+        );
+        inputs.advance_frame();
+        assert_eq!(
+            inputs
+                .text_input(window_id)
+                .unwrap()
+                .preedit()
+                .unwrap()
+                .text,
+            "にほ"
+        );
+
+        inputs.simulate_commit(window_id, "日本");
+        let text_input = inputs.text_input(window_id).unwrap();
+        assert_eq!(text_input.preedit(), None);
+        assert_eq!(text_input.text(), "日本");
+    }
+
+    #[test]
+    fn memory_clipboard() {
+        let inputs = Inputs::new();
+        assert_eq!(inputs.clipboard_text(), None);
+        inputs.set_clipboard_text("copied");
+        assert_eq!(inputs.clipboard_text().as_deref(), Some("copied"));
+    }
+}
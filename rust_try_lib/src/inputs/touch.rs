@@ -0,0 +1,387 @@
+//!Touch points of a window and gestures recognized from them.
+//!winit doesn't tell trackpad gestures yet, so only touchscreens make gestures.
+use cgmath::*;
+
+use winit::event::{Touch, TouchPhase, WindowEvent};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub phase: TouchPhase,
+    pub position: Point2<f32>,
+    pub start_position: Point2<f32>,
+    pub start_time: f64,
+    ///0 to 1. None when the device doesn't tell.
+    pub force: Option<f32>,
+
+    ///Went out of slop, so it's not a tap or long press.
+    moved: bool,
+    long_pressed: bool,
+}
+
+impl TouchPoint {
+    pub fn is_active(&self) -> bool {
+        matches!(self.phase, TouchPhase::Started | TouchPhase::Moved)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    Tap {
+        position: Point2<f32>,
+    },
+    ///Once when held in place for long_press_duration.
+    LongPress {
+        position: Point2<f32>,
+    },
+    ///One finger moved at this frame.
+    Pan {
+        delta: Vector2<f32>,
+    },
+    ///Two fingers. scale is ratio of the distance between them to the previous frame.
+    Pinch {
+        center: Point2<f32>,
+        scale: f32,
+    },
+    ///Two fingers. Clockwise on screen is positive.
+    Rotate {
+        center: Point2<f32>,
+        angle: Rad<f32>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchSettings {
+    ///Max seconds for a tap.
+    pub tap_duration: f64,
+    pub long_press_duration: f64,
+    ///Pixels a touch can move and still be a tap or long press.
+    pub slop: f32,
+    ///Primary touch moves cursor and presses the left mouse button.
+    pub emulate_mouse: bool,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        Self {
+            tap_duration: 0.3,
+            long_press_duration: 0.5,
+            slop: 10.0,
+            emulate_mouse: false,
+        }
+    }
+}
+
+pub struct Touches {
+    ///Active ones, and ones ended at this frame.
+    points: Vec<TouchPoint>,
+    gestures: Vec<Gesture>,
+
+    touchpad_pressure: f32,
+    touchpad_stage: i64,
+
+    time: f64,
+    settings: TouchSettings,
+}
+
+impl Touches {
+    pub fn new() -> Self {
+        Self {
+            points: Vec::new(),
+            gestures: Vec::new(),
+
+            touchpad_pressure: 0.0,
+            touchpad_stage: 0,
+
+            time: 0.0,
+            settings: TouchSettings::default(),
+        }
+    }
+
+    pub fn points(&self) -> &[TouchPoint] {
+        &self.points
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.points.iter().filter(|point| point.is_active())
+    }
+
+    pub fn get(&self, id: u64) -> Option<&TouchPoint> {
+        self.points.iter().find(|point| point.id == id)
+    }
+
+    ///The earliest active touch.
+    pub fn primary(&self) -> Option<&TouchPoint> {
+        self.active().next()
+    }
+
+    ///Recognized at this frame in order.
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+
+    ///0 to 1, on macOS trackpads.
+    pub fn touchpad_pressure(&self) -> f32 {
+        self.touchpad_pressure
+    }
+
+    ///Click level of force touch trackpads.
+    pub fn touchpad_stage(&self) -> i64 {
+        self.touchpad_stage
+    }
+
+    pub fn settings(&self) -> TouchSettings {
+        self.settings
+    }
+}
+
+impl Touches {
+    pub(crate) fn pre_update(&mut self, time: f64) {
+        self.time = time;
+        self.points.retain(|point| point.is_active());
+        self.gestures.clear();
+
+        for point in &mut self.points {
+            if !point.moved
+                && !point.long_pressed
+                && time - point.start_time >= self.settings.long_press_duration
+            {
+                point.long_pressed = true;
+                self.gestures.push(Gesture::LongPress {
+                    position: point.position,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn set_settings(&mut self, settings: TouchSettings) {
+        self.settings = settings;
+    }
+
+    pub(crate) fn handle_input(&mut self, input: &WindowEvent) {
+        match *input {
+            WindowEvent::Touch(touch) => self.handle_touch(touch),
+            WindowEvent::TouchpadPressure {
+                pressure, stage, ..
+            } => {
+                self.touchpad_pressure = pressure;
+                self.touchpad_stage = stage;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_touch(&mut self, touch: Touch) {
+        let position = point2(touch.location.x as f32, touch.location.y as f32);
+        let force = touch.force.map(|force| force.normalized() as f32);
+
+        if touch.phase == TouchPhase::Started {
+            self.points.retain(|point| point.id != touch.id);
+            self.points.push(TouchPoint {
+                id: touch.id,
+                phase: TouchPhase::Started,
+                position,
+                start_position: position,
+                start_time: self.time,
+                force,
+
+                moved: false,
+                long_pressed: false,
+            });
+            return;
+        }
+
+        let index = match self.points.iter().position(|point| point.id == touch.id) {
+            Some(index) => index,
+            None => return,
+        };
+        let previous = self.points[index].position;
+        if touch.phase == TouchPhase::Moved {
+            self.handle_motion(index, previous, position);
+        }
+
+        let settings = self.settings;
+        let time = self.time;
+        let point = &mut self.points[index];
+        point.phase = touch.phase;
+        point.position = position;
+        point.force = force;
+        if point.start_position.distance(position) > settings.slop {
+            point.moved = true;
+        }
+
+        if touch.phase == TouchPhase::Ended
+            && !point.moved
+            && !point.long_pressed
+            && time - point.start_time <= settings.tap_duration
+        {
+            self.gestures.push(Gesture::Tap { position });
+        }
+    }
+
+    fn handle_motion(&mut self, index: usize, previous: Point2<f32>, position: Point2<f32>) {
+        let others: Vec<Point2<f32>> = self
+            .points
+            .iter()
+            .enumerate()
+            .filter(|(other, point)| *other != index && point.is_active())
+            .map(|(_, point)| point.position)
+            .collect();
+
+        match others.as_slice() {
+            [] => {
+                let moved = self.points[index].moved
+                    || self.points[index].start_position.distance(position) > self.settings.slop;
+                if moved {
+                    self.push_pan(position - previous);
+                }
+            }
+            [other] => {
+                let before = previous - other;
+                let after = position - other;
+                if before.magnitude() <= f32::EPSILON || after.magnitude() <= f32::EPSILON {
+                    return;
+                }
+                let center = other.midpoint(position);
+                self.push_pinch(center, after.magnitude() / before.magnitude());
+                self.push_rotate(center, before.angle(after));
+            }
+            _ => {}
+        }
+    }
+
+    //Merged into one gesture per frame.
+
+    fn push_pan(&mut self, delta: Vector2<f32>) {
+        for gesture in &mut self.gestures {
+            if let Gesture::Pan { delta: total } = gesture {
+                *total += delta;
+                return;
+            }
+        }
+        self.gestures.push(Gesture::Pan { delta });
+    }
+
+    fn push_pinch(&mut self, center: Point2<f32>, scale: f32) {
+        for gesture in &mut self.gestures {
+            if let Gesture::Pinch {
+                center: last_center,
+                scale: total,
+            } = gesture
+            {
+                *last_center = center;
+                *total *= scale;
+                return;
+            }
+        }
+        self.gestures.push(Gesture::Pinch { center, scale });
+    }
+
+    fn push_rotate(&mut self, center: Point2<f32>, angle: Rad<f32>) {
+        for gesture in &mut self.gestures {
+            if let Gesture::Rotate {
+                center: last_center,
+                angle: total,
+            } = gesture
+            {
+                *last_center = center;
+                *total += angle;
+                return;
+            }
+        }
+        self.gestures.push(Gesture::Rotate { center, angle });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::inputs::{Inputs, MouseButton};
+    use winit::window::WindowId;
+
+    #[test]
+    fn tap_and_long_press() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_touch(window_id, 0, TouchPhase::Started, point2(5.0, 5.0));
+        inputs.advance_frame_by(0.1);
+        inputs.simulate_touch(window_id, 0, TouchPhase::Ended, point2(6.0, 5.0));
+        assert_eq!(
+            inputs.touches(window_id).unwrap().gestures(),
+            &[Gesture::Tap {
+                position: point2(6.0, 5.0)
+            }]
+        );
+
+        inputs.simulate_touch(window_id, 1, TouchPhase::Started, point2(5.0, 5.0));
+        inputs.advance_frame_by(0.6);
+        assert_eq!(
+            inputs.touches(window_id).unwrap().gestures(),
+            &[Gesture::LongPress {
+                position: point2(5.0, 5.0)
+            }]
+        );
+        inputs.advance_frame_by(0.1);
+        inputs.simulate_touch(window_id, 1, TouchPhase::Ended, point2(5.0, 5.0));
+        assert!(inputs.touches(window_id).unwrap().gestures().is_empty());
+    }
+
+    #[test]
+    fn pan_pinch_rotate() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+
+        inputs.simulate_touch(window_id, 0, TouchPhase::Started, point2(0.0, 0.0));
+        inputs.simulate_touch(window_id, 0, TouchPhase::Moved, point2(20.0, 0.0));
+        inputs.simulate_touch(window_id, 0, TouchPhase::Moved, point2(30.0, 0.0));
+        assert_eq!(
+            inputs.touches(window_id).unwrap().gestures(),
+            &[Gesture::Pan {
+                delta: vec2(30.0, 0.0)
+            }]
+        );
+
+        inputs.advance_frame();
+        inputs.simulate_touch(window_id, 1, TouchPhase::Started, point2(10.0, 0.0));
+        inputs.simulate_touch(window_id, 1, TouchPhase::Moved, point2(30.0, 20.0));
+        let touches = inputs.touches(window_id).unwrap();
+        assert_eq!(touches.active().count(), 2);
+        match touches.gestures() {
+            [Gesture::Pinch { scale, .. }, Gesture::Rotate { angle, .. }] => {
+                assert!((scale - 1.0).abs() < 1e-5);
+                //Left to down is counterclockwise on screen.
+                assert!((angle.0 + std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+            }
+            gestures => panic!("{:?}", gestures),
+        }
+    }
+
+    #[test]
+    fn emulates_mouse() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        inputs.set_touch_settings(TouchSettings {
+            emulate_mouse: true,
+            ..TouchSettings::default()
+        });
+
+        inputs.simulate_touch(window_id, 3, TouchPhase::Started, point2(7.0, 8.0));
+        inputs.simulate_touch(window_id, 4, TouchPhase::Started, point2(70.0, 80.0));
+        assert!(inputs
+            .window_mouse(window_id)
+            .unwrap()
+            .is_just_pressed(MouseButton::Left));
+        assert_eq!(
+            inputs.cursor(window_id).unwrap().position(),
+            point2(7.0, 8.0)
+        );
+
+        inputs.simulate_touch(window_id, 3, TouchPhase::Ended, point2(7.0, 8.0));
+        assert!(inputs
+            .window_mouse(window_id)
+            .unwrap()
+            .is_released(MouseButton::Left));
+    }
+}
@@ -20,6 +20,9 @@ macro_rules! leaf_mod{
 #[macro_use]
 pub extern crate wgpu;
 
+//Derived code refers to this crate by name, inside this crate too.
+extern crate self as rust_try_lib;
+
 pub use cgmath;
 pub use winit;
 
@@ -27,31 +30,48 @@ pub mod application {
     leaf_mod! {pub application}
     leaf_mod! {pub event}
     leaf_mod! {pub scene}
+    leaf_mod! {pub windows}
 }
 
 pub mod graphics {
     pub mod elements {
+        leaf_mod! {pub indirect}
         leaf_mod! {pub model}
         leaf_mod! {pub texture}
         leaf_mod! {pub vertex}
     }
+    leaf_mod! {pub compute}
+    leaf_mod! {pub debug_draw}
+    leaf_mod! {pub error}
     leaf_mod! {pub graphics}
+    leaf_mod! {pub indirect}
+    leaf_mod! {pub particle}
     leaf_mod! {pub renderer_on_dev}
     leaf_mod! {pub renderer}
+    leaf_mod! {pub skybox}
 }
 
 pub mod inputs {
     leaf_mod! {pub buttons}
+    leaf_mod! {pub chord}
     leaf_mod! {pub cursor}
+    leaf_mod! {pub gamepad}
+    leaf_mod! {pub input_config}
+    leaf_mod! {pub input_map}
     leaf_mod! {pub inputs}
     leaf_mod! {pub keyboard}
     leaf_mod! {pub mock}
     leaf_mod! {pub mouse}
+    leaf_mod! {pub recording}
+    leaf_mod! {pub text_input}
+    leaf_mod! {pub touch}
+    mod simulate;
 }
 
 pub mod utils {
     leaf_mod! {pub macros}
     leaf_mod! {pub time}
+    leaf_mod! {pub timer}
     leaf_mod! {pub utils}
     leaf_mod! {pub wrapper}
 }
@@ -1,11 +1,9 @@
+///Byte offset of the field. No value of the type is made.
 #[macro_export]
 macro_rules! offset_of {
-    ($base:path, $field:ident) => {{
-        unsafe {
-            let b: $base = std::mem::zeroed();
-            (&b.$field as *const _ as isize) - (&b as *const _ as isize)
-        }
-    }};
+    ($base:ty, $field:ident) => {
+        ::core::mem::offset_of!($base, $field) as isize
+    };
 }
 
 ///Currently for the Application.rs. for DRY
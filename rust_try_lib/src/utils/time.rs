@@ -1,59 +1,353 @@
 //!Provides global time per frame.
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 use std::time::*;
 
+///Source of time in seconds from an arbitrary origin, which never goes back.
+pub trait Clock: Send {
+    fn now(&self) -> f64;
+}
+
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.origin.elapsed().as_secs_f64()
+    }
+}
+
+///Moves only when told to, for deterministic tests.
+///Clones share the same time, so one can be kept to drive the one given to Time.
+#[derive(Clone)]
+pub struct ManualClock {
+    seconds: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            seconds: Arc::new(AtomicU64::new(0f64.to_bits())),
+        }
+    }
+
+    pub fn set(&self, seconds: f64) {
+        self.seconds.store(seconds.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.set(self.now() + seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        f64::from_bits(self.seconds.load(Ordering::Relaxed))
+    }
+}
+
+///Scaled time is what the game sees. It slows down with the time scale and stops while paused.
+///Real time keeps going regardless.
 pub struct Time {
-    base_instant: Instant,
+    clock: Box<dyn Clock>,
+    base: f64,
+
+    real_time: f64,
+    unscaled_delta: f64,
+
     time: f64,
     delta: f64,
+
+    time_scale: f64,
+    paused: bool,
+    frame_count: u64,
 }
 
 impl Time {
-    pub(crate) fn new() -> Self {
+    pub fn with_clock(clock: impl Clock + 'static) -> Self {
+        let base = clock.now();
         Self {
-            base_instant: Instant::now(),
+            clock: Box::new(clock),
+            base,
+
+            real_time: 0.0,
+            unscaled_delta: 0.0,
+
             time: 0.0,
             delta: 0.0,
+
+            time_scale: 1.0,
+            paused: false,
+            frame_count: 0,
         }
     }
 
+    ///Scaled time at this frame.
     pub fn time(&self) -> f64 {
         self.time
     }
 
+    ///Scaled delta. 0 while paused.
     pub fn delta(&self) -> f64 {
         self.delta
     }
 
+    ///Real time at this frame.
+    pub fn real_time(&self) -> f64 {
+        self.real_time
+    }
+
+    ///Real delta, regardless of the time scale and pausing.
+    pub fn unscaled_delta(&self) -> f64 {
+        self.unscaled_delta
+    }
+
+    ///Real time right now, not at this frame.
     pub fn elapsed(&self) -> f64 {
-        self.base_instant.elapsed().as_secs_f64()
+        self.clock.now() - self.base
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    ///Negative scale is taken as 0.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    ///Takes effect from the next frame.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
     }
 }
 
 impl Time {
+    ///Real time continues from here by the clock.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.base = clock.now() - self.real_time;
+        self.clock = Box::new(clock);
+    }
+
+    ///Moves a frame by given delta instead of the clock, for replays and tests.
+    pub fn advance(&mut self, delta: f64) {
+        self.step(delta);
+        //So the clock continues from here afterwards.
+        self.base = self.clock.now() - self.real_time;
+    }
+
     pub(crate) fn pre_update(&mut self) {
-        let this_instant = Instant::now();
-        let past_time = self.time;
+        let real_time = self.clock.now() - self.base;
+        if real_time < self.real_time {
+            //Clock went back, e.g. replaced. Start over from it.
+            self.base = self.clock.now() - self.real_time;
+            self.step(0.0);
+        } else {
+            self.step(real_time - self.real_time);
+        }
+    }
+
+    fn step(&mut self, unscaled_delta: f64) {
+        self.real_time += unscaled_delta;
+        self.unscaled_delta = unscaled_delta;
+
+        self.delta = if self.paused {
+            0.0
+        } else {
+            unscaled_delta * self.time_scale
+        };
+        self.time += self.delta;
+        self.frame_count += 1;
+    }
+}
+
+///Rolling statistics of recent frame times in seconds.
+pub struct FrameStats {
+    frame_times: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl FrameStats {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
 
-        match this_instant.checked_duration_since(self.base_instant) {
-            Some(duration) => {
-                self.time = duration.as_secs_f64();
-                self.delta = self.time - past_time;
-            }
-            None => {
-                self.base_instant = this_instant;
-                self.time = 0.0;
-                self.delta = 0.0;
-            }
+    pub(crate) fn push(&mut self, frame_time: f64) {
+        if self.frame_times.len() == self.capacity {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    ///Number of frames the statistics are over.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    ///Drops the oldest frames when it gets smaller.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.frame_times.len() > self.capacity {
+            self.frame_times.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frame_times.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frame_times.clear();
+    }
+
+    ///Most recent frame time. 0 when empty, as do the others.
+    pub fn last(&self) -> f64 {
+        self.frame_times.back().copied().unwrap_or(0.0)
+    }
+
+    pub fn min(&self) -> f64 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .unwrap_or(0.0)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.frame_times
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    ///Nearest rank, percent in 0..=100. e.g. 99 gives the frame time 99% of frames are within.
+    pub fn percentile(&self, percent: f64) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f64> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    ///From the average frame time.
+    pub fn fps(&self) -> f64 {
+        let average = self.average();
+        if average > 0.0 {
+            1.0 / average
+        } else {
+            0.0
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
+    #[test]
+    fn scale_and_pause() {
+        let clock = ManualClock::new();
+        let mut time = Time::with_clock(clock.clone());
+
+        clock.advance(1.0);
+        time.pre_update();
+        assert_eq!(
+            (time.time(), time.delta(), time.real_time()),
+            (1.0, 1.0, 1.0)
+        );
+
+        time.set_time_scale(0.5);
+        clock.advance(1.0);
+        time.pre_update();
+        assert_eq!(
+            (time.time(), time.delta(), time.unscaled_delta()),
+            (1.5, 0.5, 1.0)
+        );
+
+        time.pause();
+        clock.advance(1.0);
+        time.pre_update();
+        assert_eq!(
+            (time.time(), time.delta(), time.real_time()),
+            (1.5, 0.0, 3.0)
+        );
+        assert_eq!(time.unscaled_delta(), 1.0);
+
+        time.resume();
+        time.advance(2.0);
+        assert_eq!((time.time(), time.real_time()), (2.5, 5.0));
+        //Clock continues from the replayed time.
+        assert_eq!(time.elapsed(), 5.0);
+        clock.advance(1.0);
+        time.pre_update();
+        assert_eq!((time.time(), time.real_time()), (3.0, 6.0));
+        assert_eq!(time.frame_count(), 5);
+    }
+
     #[test]
-    fn what_is_nan() {
-        println!("{}", 0f32 / 0f32);
-        println!("{}", f64::NAN == f64::NAN);
-        println!("{}", f64::INFINITY == f64::INFINITY);
+    fn frame_stats() {
+        let mut stats = FrameStats::new(4);
+        assert_eq!(
+            (stats.average(), stats.fps(), stats.percentile(50.0)),
+            (0.0, 0.0, 0.0)
+        );
+
+        for frame_time in [0.5, 0.1, 0.4, 0.2, 0.3] {
+            stats.push(frame_time);
+        }
+        //0.5 is rolled out.
+        assert_eq!(stats.len(), 4);
+        assert_eq!((stats.min(), stats.max(), stats.last()), (0.1, 0.4, 0.3));
+        assert!((stats.average() - 0.25).abs() < 1e-9);
+        assert!((stats.fps() - 4.0).abs() < 1e-9);
+        assert_eq!(stats.percentile(0.0), 0.1);
+        assert_eq!(stats.percentile(50.0), 0.2);
+        assert_eq!(stats.percentile(75.0), 0.3);
+        assert_eq!(stats.percentile(100.0), 0.4);
+
+        stats.set_capacity(2);
+        assert_eq!((stats.min(), stats.max()), (0.2, 0.3));
     }
 }
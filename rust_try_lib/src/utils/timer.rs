@@ -0,0 +1,313 @@
+//!Countdowns and intervals, polled or with callbacks.
+
+type Callback = Box<dyn FnMut() + Send>;
+
+///Firings of a repeating timer in a tick are capped, so tiny intervals can't stall a frame.
+const MAX_FIRED_PER_TICK: u32 = 64;
+
+///Ticked by a delta of any time, scaled or not.
+#[derive(Debug, Clone)]
+pub struct Timer {
+    duration: f64,
+    elapsed: f64,
+    repeating: bool,
+    paused: bool,
+    finished: bool,
+    fired: u32,
+}
+
+impl Timer {
+    ///Fires once after the seconds.
+    pub fn once(seconds: f64) -> Self {
+        Self::new(seconds, false)
+    }
+
+    ///Fires every interval. Intervals shorter than a frame fire several times in a tick.
+    ///Interval of 0 fires once every tick.
+    pub fn repeating(interval: f64) -> Self {
+        Self::new(interval, true)
+    }
+
+    fn new(duration: f64, repeating: bool) -> Self {
+        Self {
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+            repeating,
+            paused: false,
+            finished: false,
+            fired: 0,
+        }
+    }
+
+    ///Returns how many times it fired during the delta.
+    pub fn tick(&mut self, delta: f64) -> u32 {
+        self.fired = 0;
+        if self.paused || self.finished {
+            return 0;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            return 0;
+        }
+        if self.repeating && self.duration <= 0.0 {
+            self.fired = 1;
+            self.elapsed = 0.0;
+        } else if self.repeating {
+            let fired = (self.elapsed / self.duration).floor();
+            if fired > MAX_FIRED_PER_TICK as f64 {
+                //Too far behind to catch up. Drops the rest.
+                self.fired = MAX_FIRED_PER_TICK;
+                self.elapsed = 0.0;
+            } else {
+                self.fired = fired as u32;
+                self.elapsed -= fired * self.duration;
+            }
+        } else {
+            self.fired = 1;
+            self.elapsed = self.duration;
+            self.finished = true;
+        }
+        self.fired
+    }
+
+    ///Times fired at the last tick, for polling.
+    pub fn fired(&self) -> u32 {
+        self.fired
+    }
+
+    pub fn just_fired(&self) -> bool {
+        self.fired > 0
+    }
+
+    ///Always false when repeating.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_repeating(&self) -> bool {
+        self.repeating
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    ///Until it fires next.
+    pub fn remaining(&self) -> f64 {
+        self.duration - self.elapsed
+    }
+
+    ///0 at start, 1 when it fires.
+    pub fn fraction(&self) -> f64 {
+        if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.finished = false;
+        self.fired = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerHandle(u64);
+
+struct Entry {
+    handle: TimerHandle,
+    timer: Timer,
+    callback: Option<Callback>,
+}
+
+///Timers of the scene, ticked by scaled time every frame.
+///Finished ones with callbacks are removed. Polled ones stay until cancelled.
+pub struct Timers {
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+impl Timers {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    ///To poll with get.
+    pub fn add(&mut self, timer: Timer) -> TimerHandle {
+        self.insert(timer, None)
+    }
+
+    ///Called as many times as it fires.
+    pub fn add_with<F>(&mut self, timer: Timer, callback: F) -> TimerHandle
+    where
+        F: 'static + FnMut() + Send,
+    {
+        self.insert(timer, Some(Box::new(callback)))
+    }
+
+    pub fn after<F>(&mut self, seconds: f64, callback: F) -> TimerHandle
+    where
+        F: 'static + FnOnce() + Send,
+    {
+        let mut callback = Some(callback);
+        self.add_with(Timer::once(seconds), move || {
+            if let Some(callback) = callback.take() {
+                callback()
+            }
+        })
+    }
+
+    pub fn every<F>(&mut self, interval: f64, callback: F) -> TimerHandle
+    where
+        F: 'static + FnMut() + Send,
+    {
+        self.add_with(Timer::repeating(interval), callback)
+    }
+
+    fn insert(&mut self, timer: Timer, callback: Option<Callback>) -> TimerHandle {
+        let handle = TimerHandle(self.next_id);
+        self.next_id += 1;
+        self.entries.push(Entry {
+            handle,
+            timer,
+            callback,
+        });
+        handle
+    }
+
+    ///None when it's cancelled, or finished with a callback.
+    pub fn get(&self, handle: TimerHandle) -> Option<&Timer> {
+        self.entries
+            .iter()
+            .find(|entry| entry.handle == handle)
+            .map(|entry| &entry.timer)
+    }
+
+    ///To pause, resume or reset.
+    pub fn get_mut(&mut self, handle: TimerHandle) -> Option<&mut Timer> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.handle == handle)
+            .map(|entry| &mut entry.timer)
+    }
+
+    ///False when it's already gone.
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.handle != handle);
+        self.entries.len() != len
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    ///Callbacks are called in added order.
+    pub(crate) fn update(&mut self, delta: f64) {
+        for entry in &mut self.entries {
+            let fired = entry.timer.tick(delta);
+            if let Some(ref mut callback) = entry.callback {
+                for _ in 0..fired {
+                    callback();
+                }
+            }
+        }
+        self.entries
+            .retain(|entry| entry.callback.is_none() || !entry.timer.is_finished());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn once_and_repeating() {
+        let mut once = Timer::once(1.0);
+        assert_eq!(once.tick(0.6), 0);
+        assert!((once.remaining() - 0.4).abs() < 1e-9);
+        assert_eq!(once.tick(0.6), 1);
+        assert!(once.just_fired() && once.is_finished());
+        assert_eq!(once.tick(1.0), 0);
+        once.reset();
+        assert!(!once.is_finished());
+
+        let mut repeating = Timer::repeating(0.25);
+        assert_eq!(repeating.tick(0.6), 2);
+        assert!((repeating.fraction() - 0.4).abs() < 1e-9);
+        repeating.pause();
+        assert_eq!(repeating.tick(1.0), 0);
+        repeating.resume();
+        assert_eq!(repeating.tick(0.2), 1);
+        assert!(!repeating.is_finished());
+    }
+
+    #[test]
+    fn tiny_intervals_are_bounded() {
+        let mut every_tick = Timer::repeating(0.0);
+        assert_eq!(every_tick.tick(0.016), 1);
+        assert_eq!(every_tick.tick(0.0), 1);
+
+        let mut timers = Timers::new();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        timers.every(1e-12, move || *counter.lock().unwrap() += 1);
+        timers.update(1.0);
+        assert_eq!(*calls.lock().unwrap(), MAX_FIRED_PER_TICK);
+    }
+
+    #[test]
+    fn callbacks_and_polling() {
+        let mut timers = Timers::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let log = calls.clone();
+        timers.after(1.0, move || log.lock().unwrap().push("after"));
+        let log = calls.clone();
+        let every = timers.every(0.5, move || log.lock().unwrap().push("every"));
+        let polled = timers.add(Timer::once(0.5));
+
+        timers.update(0.5);
+        assert_eq!(*calls.lock().unwrap(), vec!["every"]);
+        assert!(timers.get(polled).unwrap().just_fired());
+
+        timers.update(0.5);
+        assert_eq!(*calls.lock().unwrap(), vec!["every", "after", "every"]);
+        //Finished polled timer stays, finished callback is gone.
+        assert!(timers.get(polled).unwrap().is_finished());
+        assert_eq!(timers.len(), 2);
+
+        assert!(timers.cancel(every));
+        assert!(!timers.cancel(every));
+        timers.update(1.0);
+        assert_eq!(calls.lock().unwrap().len(), 3);
+    }
+}
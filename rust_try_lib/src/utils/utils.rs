@@ -1,18 +1,49 @@
-use super::time::*;
+use super::{time::*, timer::*};
+
+///Frame times kept for statistics by default, 2 seconds at 60 fps.
+const FRAME_STATS_CAPACITY: usize = 120;
 
 pub struct Utils {
     time: Time,
+    frame_stats: FrameStats,
+    timers: Timers,
 }
 
 impl Utils {
     pub(crate) fn new() -> Self {
-        Self { time: Time::new() }
+        Self::with_clock(SystemClock::new())
+    }
+
+    ///Time from the clock instead of the system, for deterministic tests.
+    pub fn with_clock(clock: impl Clock + 'static) -> Self {
+        Self {
+            time: Time::with_clock(clock),
+            frame_stats: FrameStats::new(FRAME_STATS_CAPACITY),
+            timers: Timers::new(),
+        }
     }
 }
 
 impl Utils {
+    ///Keeps the time, timers and frame statistics. Time continues from here by the clock.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.time.set_clock(clock)
+    }
+
     pub(crate) fn pre_update(&mut self) {
-        self.time.pre_update()
+        self.time.pre_update();
+        self.post_time();
+    }
+
+    ///Moves a frame by given delta instead of the clock, for replays and tests.
+    pub fn advance(&mut self, delta: f64) {
+        self.time.advance(delta);
+        self.post_time();
+    }
+
+    fn post_time(&mut self) {
+        self.frame_stats.push(self.time.unscaled_delta());
+        self.timers.update(self.time.delta());
     }
 }
 
@@ -28,4 +59,109 @@ impl Utils {
     pub fn time_elapsed(&self) -> f64 {
         self.time.elapsed()
     }
+
+    pub fn real_time(&self) -> f64 {
+        self.time.real_time()
+    }
+
+    pub fn unscaled_time_delta(&self) -> f64 {
+        self.time.unscaled_delta()
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.time.frame_count()
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time.time_scale()
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time.set_time_scale(time_scale)
+    }
+
+    pub fn is_time_paused(&self) -> bool {
+        self.time.is_paused()
+    }
+
+    pub fn pause_time(&mut self) {
+        self.time.pause()
+    }
+
+    pub fn resume_time(&mut self) {
+        self.time.resume()
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    pub fn frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    pub fn timers_mut(&mut self) -> &mut Timers {
+        &mut self.timers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn timers_follow_scaled_time() {
+        let clock = ManualClock::new();
+        let mut utils = Utils::with_clock(clock.clone());
+        let fired = Arc::new(Mutex::new(0));
+
+        let counter = fired.clone();
+        utils
+            .timers_mut()
+            .every(1.0, move || *counter.lock().unwrap() += 1);
+
+        utils.set_time_scale(0.5);
+        clock.advance(1.0);
+        utils.pre_update();
+        assert_eq!(*fired.lock().unwrap(), 0);
+
+        utils.pause_time();
+        clock.advance(4.0);
+        utils.pre_update();
+        assert_eq!(*fired.lock().unwrap(), 0);
+
+        utils.resume_time();
+        clock.advance(1.0);
+        utils.pre_update();
+        assert_eq!(*fired.lock().unwrap(), 1);
+
+        //Real frame times regardless.
+        assert_eq!(utils.frame_stats().max(), 4.0);
+        assert_eq!(utils.real_time(), 6.0);
+        assert_eq!(utils.time(), 1.0);
+    }
+
+    #[test]
+    fn set_clock_keeps_state() {
+        let mut utils = Utils::with_clock(ManualClock::new());
+        let polled = utils.timers_mut().add(Timer::once(2.0));
+        utils.advance(1.0);
+
+        let clock = ManualClock::new();
+        clock.set(100.0);
+        utils.set_clock(clock.clone());
+        assert_eq!(utils.time_elapsed(), 1.0);
+
+        clock.advance(1.0);
+        utils.pre_update();
+        assert_eq!(utils.real_time(), 2.0);
+        assert!(utils.timers().get(polled).unwrap().is_finished());
+        assert_eq!(utils.frame_stats().len(), 2);
+    }
 }
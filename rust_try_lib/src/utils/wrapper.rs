@@ -1,58 +1,126 @@
 //!Various wrappers for various purposes
+#![forbid(unsafe_code)]
 
 use std::any::Any;
-use std::cell::UnsafeCell;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
-use std::sync::Once;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LazyState {
+    Uninit,
+    Init,
+    ///Finished, so it can't be initialized again.
+    Fin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LazyError {
+    Uninit,
+    AlreadyInit,
+    Fin,
+}
+
+impl fmt::Display for LazyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LazyError::Uninit => write!(f, "lazy value is not initialized yet"),
+            LazyError::AlreadyInit => write!(f, "lazy value is already initialized"),
+            LazyError::Fin => write!(f, "lazy value is already finished"),
+        }
+    }
+}
 
-///This wrapper assumes that value has ever initialized before using it.
-pub struct LazyManual<T>(UnsafeCell<Option<T>>, Once, Once);
+impl std::error::Error for LazyError {}
+
+///Value initialized once after construction, and finished once.
+///Using it outside of the two panics, or returns LazyError with try_ ones.
+pub struct LazyManual<T> {
+    value: OnceLock<T>,
+    finished: bool,
+}
 
 impl<T> LazyManual<T> {
     pub const fn new() -> Self {
-        Self(UnsafeCell::new(None), Once::new(), Once::new())
+        Self {
+            value: OnceLock::new(),
+            finished: false,
+        }
+    }
+
+    pub fn state(&self) -> LazyState {
+        if self.finished {
+            LazyState::Fin
+        } else if self.value.get().is_some() {
+            LazyState::Init
+        } else {
+            LazyState::Uninit
+        }
     }
 
-    ///initializes value. Does nothing when value has already initialized
+    ///Err with the item when it's already initialized or finished.
+    pub fn try_init(&self, item: T) -> Result<(), (LazyError, T)> {
+        if self.finished {
+            return Err((LazyError::Fin, item));
+        }
+        self.value
+            .set(item)
+            .map_err(|item| (LazyError::AlreadyInit, item))
+    }
+
+    ///Panics when it's already initialized or finished.
     pub fn init(&self, item: T) {
-        self.1.call_once(|| {
-            unsafe { *self.0.get() = Some(item) };
-        })
+        if let Err((error, _)) = self.try_init(item) {
+            panic!("{}", error);
+        }
+    }
+
+    ///Drops the value. Returns it, or None when it was not initialized.
+    pub fn fin(&mut self) -> Option<T> {
+        self.finished = true;
+        self.value.take()
     }
 
-    pub fn fin(&self) {
-        self.2.call_once(|| {
-            unsafe { *self.0.get() = None };
-        })
+    pub fn try_get(&self) -> Result<&T, LazyError> {
+        match self.value.get() {
+            Some(item) => Ok(item),
+            None if self.finished => Err(LazyError::Fin),
+            None => Err(LazyError::Uninit),
+        }
+    }
+
+    pub fn try_get_mut(&mut self) -> Result<&mut T, LazyError> {
+        let finished = self.finished;
+        match self.value.get_mut() {
+            Some(item) => Ok(item),
+            None if finished => Err(LazyError::Fin),
+            None => Err(LazyError::Uninit),
+        }
     }
 
-    ///Assumes that value has initialized before use.
+    ///Panics when it's not initialized or finished.
     pub fn get(&self) -> &T {
-        unsafe {
-            match &*self.0.get() {
-                Some(ref item) => item,
-                None => {
-                    debug_assert!(false, "Attempted to use unitialized or dropped lazy value.");
-                    std::hint::unreachable_unchecked()
-                }
-            }
+        match self.try_get() {
+            Ok(item) => item,
+            Err(error) => panic!("{}", error),
         }
     }
 
     ///Same as get
     pub fn get_mut(&mut self) -> &mut T {
-        unsafe {
-            match &mut *self.0.get() {
-                Some(ref mut item) => item,
-                None => {
-                    debug_assert!(false, "Attempted to use unitialized or dropped lazy value.");
-                    std::hint::unreachable_unchecked()
-                }
-            }
+        match self.try_get_mut() {
+            Ok(item) => item,
+            Err(error) => panic!("{}", error),
         }
     }
 }
 
+impl<T> Default for LazyManual<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Deref for LazyManual<T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -66,11 +134,6 @@ impl<T> DerefMut for LazyManual<T> {
     }
 }
 
-///Doesn't leak pointer access when sent to other thread
-unsafe impl<T: Send> Send for LazyManual<T> {}
-///Has inner mutability, so rigidly this is not Sync. Use init only in main thread.
-unsafe impl<T: Sync> Sync for LazyManual<T> {}
-
 //
 
 ///This wrapper provides kinda wildcard type.
@@ -100,47 +163,69 @@ impl BoxedAny {
     }
 }
 
-///ignores lifetime of reference
-#[repr(transparent)]
-pub struct UnsafeRef<T>(*const T);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
 
-impl<T> UnsafeRef<T> {
-    pub fn new(item: &T) -> Self {
-        Self(item as *const T)
-    }
+    //unsafe_code is forbidden here. These pass under
+    //`cargo +nightly miri test -p rust_try_lib --lib utils::wrapper` as well.
 
-    ///Assumes that value has initialized before use.
-    pub fn get(&self) -> &T {
-        unsafe { &*self.0 }
+    #[test]
+    fn lazy_states() {
+        let mut lazy = LazyManual::<Vec<i32>>::new();
+        assert_eq!(lazy.state(), LazyState::Uninit);
+        assert_eq!(lazy.try_get(), Err(LazyError::Uninit));
+
+        lazy.init(vec![1]);
+        assert_eq!(lazy.state(), LazyState::Init);
+        assert_eq!(
+            lazy.try_init(vec![2]),
+            Err((LazyError::AlreadyInit, vec![2]))
+        );
+        lazy.push(3);
+        assert_eq!(*lazy, vec![1, 3]);
+
+        assert_eq!(lazy.fin(), Some(vec![1, 3]));
+        assert_eq!(lazy.state(), LazyState::Fin);
+        assert_eq!(lazy.try_get_mut(), Err(LazyError::Fin));
+        assert_eq!(lazy.try_init(vec![4]), Err((LazyError::Fin, vec![4])));
     }
-}
 
-impl<T> Deref for UnsafeRef<T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        self.get()
+    #[test]
+    #[should_panic(expected = "not initialized")]
+    fn uninit_get_panics() {
+        let lazy = LazyManual::<i32>::new();
+        let _ = *lazy;
     }
-}
 
-///Doesn't leak pointer access when sent to other thread
-unsafe impl<T: Sync> Sync for UnsafeRef<T> {}
-///No inner mutabiliy.
-unsafe impl<T: Send> Send for UnsafeRef<T> {}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    struct Test;
-    impl Test {
-        fn a(&self) {
-            10;
+    lazy_struct! {
+        struct Lazies {
+            name: &'static str,
+            -count: u32,
         }
     }
+
+    #[test]
+    fn lazy_macros() {
+        let mut lazies = lazy_construct! {
+            Lazies {
+                name: "lazies",
+                count,
+            }
+        };
+        assert_eq!(lazies.count.state(), LazyState::Uninit);
+        lazies.count.init(1);
+        *lazies.count += 1;
+        assert_eq!((lazies.name, *lazies.count), ("lazies", 2));
+    }
+
     #[test]
-    fn what() {
-        let a = Test {};
-        let b = UnsafeRef::new(&a);
-        let c = b.get();
-        c.a();
+    fn shared_between_threads() {
+        static SHARED: LazyManual<String> = LazyManual::new();
+        std::thread::spawn(|| SHARED.init("hi".to_string()))
+            .join()
+            .unwrap();
+        assert_eq!(SHARED.get(), "hi");
     }
 }
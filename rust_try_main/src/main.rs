@@ -1,11 +1,25 @@
-use rust_try_lib::application::*;
+use rust_try_lib::{application::*, inputs::InputRecording};
 
 use scenes::initial::InitialScene;
 
 fn main() {
     env_logger::init();
-    let app = Application::new("Rust Try");
-    let initial_scene = InitialScene::new(&app);
+    let mut app = match Application::new("Rust Try") {
+        Ok(app) => app,
+        Err(error) => {
+            eprintln!("Failed to start: {}", error);
+            std::process::exit(1);
+        }
+    };
+    if let Ok(path) = std::env::var("RUST_TRY_REPLAY") {
+        match InputRecording::load(&path) {
+            Ok(recording) => app.replay_inputs(recording),
+            Err(error) => eprintln!("Failed to load inputs from {}: {}", path, error),
+        }
+    } else if let Ok(path) = std::env::var("RUST_TRY_RECORD") {
+        app.record_inputs(path);
+    }
+    let initial_scene = InitialScene::new(&mut app);
     app.run(initial_scene);
 }
 
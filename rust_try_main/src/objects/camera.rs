@@ -1,6 +1,6 @@
 use crate::objects::transform::{Transform, *};
 
-use rust_try_lib::{cgmath::*, inputs::*, utils::Utils, winit, winit::window::WindowId};
+use rust_try_lib::{cgmath::*, inputs::*, winit, winit::window::WindowId};
 
 pub struct Camera {
     projection: PerspectiveFov<f32>,
@@ -75,46 +75,26 @@ impl Camera {
 }
 
 impl Camera {
-    pub fn handle_input(&mut self, target_window_id: WindowId, utils: &Utils, inputs: &Inputs) {
-        if let Some(keyboard) = inputs.window_keyboard(target_window_id) {
-            let forward = if keyboard.is_pressed(KeyCode::W) {
-                1f32
-            } else {
-                0f32
-            };
-            let backward = if keyboard.is_pressed(KeyCode::S) {
-                1f32
-            } else {
-                0f32
-            };
-            let left = if keyboard.is_pressed(KeyCode::A) {
-                1f32
-            } else {
-                0f32
-            };
-            let right = if keyboard.is_pressed(KeyCode::D) {
-                1f32
-            } else {
-                0f32
-            };
-            let up = if keyboard.is_pressed(KeyCode::Space) {
-                1f32
-            } else {
-                0f32
-            };
-            let down = if keyboard.is_pressed(KeyCode::LShift) {
-                1f32
-            } else {
-                0f32
-            };
-            self.r#move(
-                utils.time_delta() as f32,
-                vec3(forward - backward, right - left, up - down),
-            );
-        };
+    ///Bindings which handle_input reads.
+    pub fn bind_inputs(input_map: &mut InputMap) {
+        input_map
+            .bind_axis2(
+                "move",
+                Axis2Binding::composite(KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+            )
+            .bind_axis("fly", AxisBinding::buttons(KeyCode::LShift, KeyCode::Space));
+    }
+
+    ///Keys pressed in other windows don't move it.
+    pub fn handle_input(&mut self, target_window_id: WindowId, delta: f32, inputs: &Inputs) {
+        let r#move = inputs.axis2_in(target_window_id, "move");
+        let fly = inputs.axis_in(target_window_id, "fly");
+        self.r#move(delta, vec3(r#move.y, r#move.x, fly));
 
         if let Some(cursor) = inputs.cursor(target_window_id) {
-            if let Some(mouse) = inputs.device_mouse(None) {
+            if cursor.mode() == CursorMode::Relative {
+                self.rotate(inputs.pointer_motion(target_window_id));
+            } else if let Some(mouse) = inputs.device_mouse(None) {
                 let motion = if cursor.is_just_entered() {
                     mouse.last_motion()
                 } else if cursor.is_entered() {
@@ -195,3 +175,65 @@ impl Camera {
         self.transform.rotate(rotation);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::new(
+            1.0,
+            point3(0.0, 0.0, 5.0),
+            vec3(0.0, 0.0, -1.0),
+            1.0,
+            Deg(0.1),
+        )
+    }
+
+    #[test]
+    fn moves_forward_with_key() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        Camera::bind_inputs(inputs.input_map_mut());
+        let mut camera = camera();
+
+        inputs.simulate_key(window_id, KeyCode::W, ButtonState::Pressed);
+        camera.handle_input(window_id, 0.5, &inputs);
+        assert!((camera.position() - point3(0.0, 0.0, 4.5)).magnitude() < 1e-5);
+
+        inputs.advance_frame();
+        inputs.simulate_key(window_id, KeyCode::W, ButtonState::Released);
+        camera.handle_input(window_id, 0.5, &inputs);
+        assert!((camera.position() - point3(0.0, 0.0, 4.5)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn rotates_only_inside_window() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        let mut camera = camera();
+
+        inputs.simulate_mouse_motion(vec2(10.0, 0.0));
+        camera.handle_input(window_id, 0.0, &inputs);
+        assert_eq!(camera.rotation(), Quaternion::one());
+
+        inputs.advance_frame();
+        inputs.simulate_cursor_enter(window_id);
+        inputs.advance_frame();
+        inputs.simulate_mouse_motion(vec2(10.0, 0.0));
+        camera.handle_input(window_id, 0.0, &inputs);
+        assert_ne!(camera.rotation(), Quaternion::one());
+    }
+
+    #[test]
+    fn rotates_in_relative_mode() {
+        let window_id = unsafe { WindowId::dummy() };
+        let mut inputs = Inputs::new();
+        let mut camera = camera();
+
+        inputs.simulate_cursor_mode(window_id, CursorMode::Relative);
+        inputs.simulate_mouse_motion(vec2(10.0, 0.0));
+        camera.handle_input(window_id, 0.0, &inputs);
+        assert_ne!(camera.rotation(), Quaternion::one());
+    }
+}
@@ -28,6 +28,13 @@ impl<T: Into<Quaternion<f32>> + Copy> Transform<T> {
     pub fn scale(&self) -> Vector3<f32> {
         self.scale
     }
+
+    ///For DebugLines::axes
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position.to_vec())
+            * Matrix4::from(self.rotation())
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
 }
 
 impl<T: Into<Quaternion<f32>> + Copy> Transform<T> {
@@ -1,7 +1,7 @@
 use crate::objects::camera::*;
 
 use rust_try_lib::{
-    application::{Application, Scene},
+    application::{Application, EventBus, Scene, WindowCommands},
     cgmath::*,
     graphics::elements::*,
     graphics::*,
@@ -22,11 +22,16 @@ pub struct InitialScene {
 }
 
 impl InitialScene {
-    pub fn new(app: &Application) -> Self {
+    pub fn new(app: &mut Application) -> Self {
+        Camera::bind_inputs(app.inputs_mut().input_map_mut());
+        app.inputs_mut()
+            .input_map_mut()
+            .bind_action("toggle_cursor", ActionBinding::new(KeyCode::Escape));
+
         let target_window_id = app.graphics().primary_window_id().unwrap();
 
         let camera = Camera::new(
-            app.graphics().aspect(target_window_id),
+            app.graphics().aspect(target_window_id).unwrap_or(1.0),
             point3(0.0, 0.0, 5.0),
             vec3(0.0, 0.0, -1.0),
             1.0,
@@ -78,8 +83,17 @@ impl InitialScene {
 
 impl InitialScene {
     fn handle_input(&mut self, utils: &Utils, inputs: &Inputs) {
+        if inputs.action("toggle_cursor").is_just_pressed() {
+            if let Some(cursor) = inputs.cursor(self.target_window_id) {
+                let mode = match cursor.mode() {
+                    CursorMode::Relative => CursorMode::Normal,
+                    _ => CursorMode::Relative,
+                };
+                inputs.set_cursor_mode(self.target_window_id, mode);
+            }
+        }
         self.camera
-            .handle_input(self.target_window_id, utils, inputs);
+            .handle_input(self.target_window_id, utils.time_delta() as f32, inputs);
     }
 }
 
@@ -92,7 +106,13 @@ impl Scene for InitialScene {
         }
     }
 
-    fn update(&mut self, utils: &Utils, inputs: &Inputs) {
+    fn update(
+        &mut self,
+        utils: &mut Utils,
+        inputs: &Inputs,
+        _events: &mut EventBus,
+        _windows: &mut WindowCommands,
+    ) {
         self.handle_input(utils, inputs);
     }
 